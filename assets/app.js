@@ -0,0 +1,28 @@
+// the dashboard's own script, served from /static/app.js so pages need no
+// inline javascript
+
+// htmx leaves error responses unswapped, but a 409 carries a message worth
+// showing
+document.addEventListener('htmx:beforeSwap', e => {
+    if (e.detail.xhr.status === 409) {
+        e.detail.shouldSwap = true;
+        e.detail.isError = false;
+    }
+});
+
+// admin buttons ask for the token with hx-prompt, it's sent as a bearer token
+document.addEventListener('htmx:configRequest', e => {
+    if (e.detail.elt.hasAttribute('data-admin') && e.detail.headers['HX-Prompt']) {
+        e.detail.headers['Authorization'] = 'Bearer ' + e.detail.headers['HX-Prompt'];
+    }
+});
+
+// data-copy puts whatever that url answers with on the clipboard
+document.addEventListener('click', e => {
+    const button = e.target.closest('[data-copy]');
+    if (button) {
+        fetch(button.dataset.copy)
+            .then(r => r.text())
+            .then(t => navigator.clipboard.writeText(t));
+    }
+});
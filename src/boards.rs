@@ -0,0 +1,76 @@
+//! physical boards the qmk export knows how to lay a keyboard out on. keys
+//! that don't fit a board (the number row on a 40%, say) are left off, the
+//! board's own modifiers fill the rest.
+
+/// one entry of a board's `LAYOUT_` macro
+pub enum Slot {
+    /// the glyph at this position of `Keyboard::keys`
+    Key(usize),
+    /// a fixed qmk keycode
+    Code(&'static str),
+}
+
+use Slot::{Code, Key};
+
+pub struct Board {
+    pub name: &'static str,
+    pub layout_macro: &'static str,
+    /// slots in macro order, split at the board's physical rows
+    pub rows: &'static [&'static [Slot]],
+    /// how far each row of `ROWS` sits right of the first, in key widths
+    pub offsets: [f32; 4],
+}
+
+pub const BOARDS: &[Board] = &[
+    Board {
+        name: "planck",
+        layout_macro: "LAYOUT_planck_grid",
+        rows: &[
+            &[
+                Code("KC_TAB"), Key(13), Key(14), Key(15), Key(16), Key(17),
+                Key(18), Key(19), Key(20), Key(21), Key(22), Code("KC_BSPC"),
+            ],
+            &[
+                Code("KC_ESC"), Key(26), Key(27), Key(28), Key(29), Key(30),
+                Key(31), Key(32), Key(33), Key(34), Key(35), Key(36),
+            ],
+            &[
+                Code("KC_LSFT"), Key(37), Key(38), Key(39), Key(40), Key(41),
+                Key(42), Key(43), Key(44), Key(45), Key(46), Code("KC_ENT"),
+            ],
+            &[
+                Code("KC_LCTL"), Code("KC_LGUI"), Code("KC_LALT"), Code("KC_NO"), Code("KC_NO"), Code("KC_SPC"),
+                Code("KC_SPC"), Code("KC_NO"), Code("KC_NO"), Code("KC_RALT"), Code("KC_RGUI"), Code("KC_RCTL"),
+            ],
+        ],
+        offsets: [0.0; 4],
+    },
+    Board {
+        name: "corne",
+        layout_macro: "LAYOUT_split_3x6_3",
+        rows: &[
+            &[
+                Code("KC_TAB"), Key(13), Key(14), Key(15), Key(16), Key(17),
+                Key(18), Key(19), Key(20), Key(21), Key(22), Code("KC_BSPC"),
+            ],
+            &[
+                Code("KC_LCTL"), Key(26), Key(27), Key(28), Key(29), Key(30),
+                Key(31), Key(32), Key(33), Key(34), Key(35), Key(36),
+            ],
+            &[
+                Code("KC_LSFT"), Key(37), Key(38), Key(39), Key(40), Key(41),
+                Key(42), Key(43), Key(44), Key(45), Key(46), Code("KC_ESC"),
+            ],
+            &[
+                Code("KC_LGUI"), Code("KC_NO"), Code("KC_SPC"),
+                Code("KC_ENT"), Code("KC_NO"), Code("KC_RALT"),
+            ],
+        ],
+        offsets: [0.0; 4],
+    },
+];
+
+/// the board called `name`, ignoring case
+pub fn find(name: &str) -> Option<&'static Board> {
+    BOARDS.iter().find(|b| b.name.eq_ignore_ascii_case(name.trim()))
+}
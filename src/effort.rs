@@ -0,0 +1,148 @@
+//! a basic typing effort model, so the coordinator can check the scores
+//! workers report. lower is better.
+
+use std::{collections::HashMap, sync::OnceLock};
+
+use crate::{Keyboard, ROWS, ROW_OFFSETS};
+
+/// fingers left pinky to right pinky
+const FINGERS: [u8; 47] = [
+    0, 0, 1, 2, 3, 3, 4, 4, 5, 6, 7, 7, 7,
+    0, 1, 2, 3, 3, 4, 4, 5, 6, 7, 7, 7, 7,
+    0, 1, 2, 3, 3, 4, 4, 5, 6, 7, 7,
+    0, 1, 2, 3, 3, 4, 4, 5, 6, 7,
+];
+
+/// resting position of each finger
+const HOME: [usize; 8] = [26, 27, 28, 29, 32, 33, 34, 35];
+
+/// how much harder each finger works than an index finger
+const FINGER_WEIGHT: [f32; 8] = [1.6, 1.3, 1.1, 1.0, 1.0, 1.1, 1.3, 1.6];
+
+/// cost of a press that needs no travel at all
+const PRESS_COST: f32 = 1.0;
+/// extra cost of typing two different keys in a row with the same finger
+const SAME_FINGER_PENALTY: f32 = 2.0;
+
+/// english prose the built-in frequency table is counted from
+const SAMPLE: &str = "the quick brown fox jumps over the lazy dog while the \
+    keyboard waits for someone to type on it. most of what people write is \
+    made of short common words, and the letters in them are not spread out \
+    evenly. e, t, a, o, i, n, s, h and r carry most of the load, so a good \
+    layout puts them where the fingers already rest. that is easy to say and \
+    hard to get right, because the same finger typing two keys one after the \
+    other is slow, and reaching for the top or bottom row all the time is \
+    tiring. there is also punctuation to think about: commas, full stops, \
+    question marks and the odd semicolon; numbers like 1, 2, 10 and 2024 \
+    show up in dates and lists. in the end every layout is a compromise \
+    between these pressures, and the only way to compare two of them fairly \
+    is to measure how much work they take on text like this.";
+
+/// how often each character and pair of characters appears in some text
+pub struct FreqTable {
+    unigrams: HashMap<char, f32>,
+    bigrams: HashMap<(char, char), f32>,
+}
+
+impl FreqTable {
+    pub fn from_text(text: &str) -> Self {
+        let mut unigrams = HashMap::new();
+        let mut bigrams = HashMap::new();
+        let chars: Vec<char> = text.chars().flat_map(char::to_lowercase).collect();
+        for &c in &chars {
+            *unigrams.entry(c).or_insert(0.0) += 1.0;
+        }
+        for pair in chars.windows(2) {
+            *bigrams.entry((pair[0], pair[1])).or_insert(0.0) += 1.0;
+        }
+        Self { unigrams, bigrams }
+    }
+
+    pub fn english() -> &'static Self {
+        static CELL: OnceLock<FreqTable> = OnceLock::new();
+        CELL.get_or_init(|| Self::from_text(SAMPLE))
+    }
+}
+
+/// (x, y) of a key's center, in key widths
+fn coords(pos: usize) -> (f32, f32) {
+    let (row, &(start, _)) = ROWS
+        .iter()
+        .enumerate()
+        .find(|(_, &(start, end))| (start..end).contains(&pos))
+        .expect("position on the board");
+    ((pos - start) as f32 + ROW_OFFSETS[row], row as f32)
+}
+
+fn distance(a: usize, b: usize) -> f32 {
+    let ((ax, ay), (bx, by)) = (coords(a), coords(b));
+    ((ax - bx).powi(2) + (ay - by).powi(2)).sqrt()
+}
+
+pub fn finger(pos: usize) -> u8 {
+    FINGERS[pos]
+}
+
+/// where each glyph lives, by either layer
+fn positions(keyboard: &Keyboard) -> HashMap<char, usize> {
+    let mut positions = HashMap::new();
+    for (pos, key) in keyboard.keys.iter().enumerate() {
+        positions.entry(key.lower).or_insert(pos);
+        positions.insert(key.upper, pos);
+    }
+    positions
+}
+
+/// average effort per character of `corpus` typed on `keyboard`. characters
+/// the keyboard doesn't have are skipped.
+pub fn score_layout(keyboard: &Keyboard, corpus: &FreqTable) -> f32 {
+    position_costs(keyboard, corpus).iter().sum()
+}
+
+/// each position's share of `score_layout`, a same finger pair split evenly
+/// between its two keys
+pub fn position_costs(keyboard: &Keyboard, corpus: &FreqTable) -> [f32; 47] {
+    let positions = positions(keyboard);
+    let mut effort = [0.0; 47];
+    let mut typed = 0.0;
+
+    for (c, &count) in &corpus.unigrams {
+        let Some(&pos) = positions.get(c) else { continue };
+        let finger = finger(pos) as usize;
+        effort[pos] += count * (PRESS_COST + distance(pos, HOME[finger])) * FINGER_WEIGHT[finger];
+        typed += count;
+    }
+    for ((a, b), &count) in &corpus.bigrams {
+        let (Some(&a), Some(&b)) = (positions.get(a), positions.get(b)) else { continue };
+        if a != b && finger(a) == finger(b) {
+            let penalty = count * (SAME_FINGER_PENALTY + distance(a, b)) / 2.0;
+            effort[a] += penalty;
+            effort[b] += penalty;
+        }
+    }
+
+    if typed > 0.0 {
+        for cost in &mut effort {
+            *cost /= typed;
+        }
+    }
+    effort
+}
+
+/// pairs of different keys under the same finger, by how often `corpus` types
+/// one right after the other, either way round. each comes with its share of
+/// all the bigrams, most frequent first.
+pub fn same_finger_bigrams(keyboard: &Keyboard, corpus: &FreqTable) -> Vec<((usize, usize), f32)> {
+    let positions = positions(keyboard);
+    let total: f32 = corpus.bigrams.values().sum();
+    let mut pairs: HashMap<(usize, usize), f32> = HashMap::new();
+    for ((a, b), &count) in &corpus.bigrams {
+        let (Some(&a), Some(&b)) = (positions.get(a), positions.get(b)) else { continue };
+        if a != b && finger(a) == finger(b) {
+            *pairs.entry((a.min(b), a.max(b))).or_insert(0.0) += count / total;
+        }
+    }
+    let mut pairs: Vec<((usize, usize), f32)> = pairs.into_iter().collect();
+    pairs.sort_by(|(a, x), (b, y)| y.total_cmp(x).then(a.cmp(b)));
+    pairs
+}
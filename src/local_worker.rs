@@ -0,0 +1,100 @@
+//! in-process worker used when a host is registered as `self`/`local://`.
+//! it speaks the same batch protocol as a remote worker, minus the http.
+
+use std::collections::BTreeMap;
+
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+
+use crate::{
+    effort::{self, FreqTable},
+    BatchReq, Key, Keyboard,
+};
+
+/// unshifted and shifted glyphs of a us qwerty board, in `Keyboard::keys` order
+const BASE_LAYER: &str = "`1234567890-=qwertyuiop[]\\asdfghjkl;'zxcvbnm,./";
+const SHIFT_LAYER: &str = "~!@#$%^&*()_+QWERTYUIOP{}|ASDFGHJKL:\"ZXCVBNM<>?";
+
+/// swaps tried per generated keyboard
+const CLIMB_STEPS: usize = 2_000;
+
+/// `start_layout` as keys, each with its qwerty shifted glyph or itself if
+/// qwerty doesn't have it
+fn start_keys(start_layout: &[char]) -> [Key; 47] {
+    let mut keys = [Key { lower: ' ', upper: ' ' }; 47];
+    for (key, &upper) in keys.iter_mut().zip(start_layout) {
+        let lower = BASE_LAYER
+            .chars()
+            .position(|c| c == upper)
+            .and_then(|i| SHIFT_LAYER.chars().nth(i))
+            .unwrap_or(upper);
+        *key = Key { lower, upper };
+    }
+    keys
+}
+
+fn score(keys: &[Key; 47]) -> f32 {
+    effort::score_layout(&Keyboard { id: 0, score: 0.0, keys: *keys, metrics: BTreeMap::new(), generation: None, parent_id: None }, FreqTable::english())
+}
+
+/// a climb from `start_layout` shuffled, or from `seed` as it is. only free
+/// positions, ones the job lets change and hasn't pinned, ever move.
+fn generate(
+    start_layout: &[char],
+    pinned: &[(usize, char)],
+    mutable: &[usize],
+    seed: Option<&[Key]>,
+    rng: &mut impl Rng,
+) -> Keyboard {
+    let mut keys = start_keys(start_layout);
+    if let Some(seed) = seed {
+        keys.copy_from_slice(seed);
+    }
+    let free: Vec<usize> = (0..47)
+        .filter(|i| !pinned.iter().any(|(p, _)| p == i))
+        .filter(|i| mutable.is_empty() || mutable.contains(i))
+        .collect();
+    // a pinned glyph is taken from a free position, one that's fixed keeps
+    // its glyph and the pin goes unmet
+    for &(pos, glyph) in pinned {
+        if let Some(&from) = free.iter().find(|&&i| keys[i].upper == glyph) {
+            keys.swap(pos, from);
+        }
+    }
+
+    if seed.is_none() {
+        let mut shuffled: Vec<Key> = free.iter().map(|&i| keys[i]).collect();
+        shuffled.shuffle(rng);
+        for (&i, key) in free.iter().zip(shuffled) {
+            keys[i] = key;
+        }
+    }
+
+    let mut best = score(&keys);
+    if free.len() < 2 {
+        return Keyboard { id: 0, score: best, keys, metrics: BTreeMap::new(), generation: None, parent_id: None }
+    }
+    for _ in 0..CLIMB_STEPS {
+        let (a, b) = (free[rng.gen_range(0..free.len())], free[rng.gen_range(0..free.len())]);
+        keys.swap(a, b);
+        let candidate = score(&keys);
+        if candidate < best {
+            best = candidate;
+        } else {
+            keys.swap(a, b);
+        }
+    }
+
+    Keyboard { id: 0, score: best, keys, metrics: BTreeMap::new(), generation: None, parent_id: None }
+}
+
+/// computes a whole batch from the coordinator's `start_layout`, blocking the
+/// current thread. the same seed gives the same batch.
+pub fn run_batch(req: &BatchReq, start_layout: &[char]) -> Vec<Keyboard> {
+    let mut rng = match req.seed {
+        Some(seed) => StdRng::seed_from_u64(seed),
+        None => StdRng::from_entropy(),
+    };
+    (0..req.batch_size)
+        .map(|_| generate(start_layout, &req.pinned, &req.mutable_positions, req.seed_layout.as_deref(), &mut rng))
+        .collect()
+}
@@ -0,0 +1,62 @@
+//! tracing events copied onto a broadcast channel, for streaming to `/logs`
+
+use std::fmt::{self, Write};
+
+use tokio::sync::broadcast;
+use tracing::{field::{Field, Visit}, Event, Level, Subscriber};
+use tracing_subscriber::{layer::Context, Layer};
+
+/// lines kept for each listener. one that falls further behind skips ahead
+/// rather than holding on to everything it hasn't read.
+pub const BUFFER: usize = 1024;
+
+#[derive(Clone)]
+pub struct LogLine {
+    pub level: Level,
+    /// target, message and fields, the way the console shows them
+    pub text: String,
+}
+
+/// sends every event the subscriber lets through to `tx`
+pub struct BroadcastLayer {
+    pub tx: broadcast::Sender<LogLine>,
+}
+
+impl<S: Subscriber> Layer<S> for BroadcastLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        // nobody is listening, no point formatting it
+        if self.tx.receiver_count() == 0 {
+            return
+        }
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let level = *event.metadata().level();
+        let text = format!("{} {}: {}{}", level, event.metadata().target(), fields.message, fields.rest);
+        self.tx.send(LogLine { level, text }).ok();
+    }
+}
+
+#[derive(Default)]
+struct Fields {
+    message: String,
+    /// every other field as ` name=value`
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        if field.name() == "message" {
+            self.message.push_str(value);
+        } else {
+            write!(self.rest, " {}={}", field.name(), value).ok();
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            write!(self.message, "{:?}", value).ok();
+        } else {
+            write!(self.rest, " {}={:?}", field.name(), value).ok();
+        }
+    }
+}
@@ -1,54 +1,835 @@
 use std::{
-    sync::Arc,
+    cmp::Ordering,
+    convert::Infallible,
+    future::Future,
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet, VecDeque},
+    sync::{atomic::{self, AtomicBool}, Arc},
     net::SocketAddr,
-    time::Duration,
-    thread,
+    ops::Range,
+    path::PathBuf,
+    pin::Pin,
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use axum::{
     routing::{get, post},
-    extract::State,
-    response::Html,
+    extract::{ws::{Message, WebSocket, WebSocketUpgrade}, DefaultBodyLimit, Path, Query, State},
+    http::{
+        header::{
+            ACCEPT, AUTHORIZATION, CACHE_CONTROL, CONTENT_DISPOSITION, CONTENT_SECURITY_POLICY,
+            CONTENT_TYPE, REFERRER_POLICY, X_CONTENT_TYPE_OPTIONS, X_FRAME_OPTIONS,
+        },
+        HeaderValue, Request, StatusCode,
+    },
+    middleware::{self, Next},
+    response::{sse::{self, KeepAlive, Sse}, Html, IntoResponse, Response},
+    Json,
     Router,
     Form
 };
+use futures_util::{stream, SinkExt, Stream, StreamExt};
 use serde::{Deserialize, Serialize};
 use serde_big_array::BigArray;
+use sha2::{Digest, Sha256};
 use hyprtxt::hyprtxt;
-use tokio::sync::Mutex;
+use tokio::{
+    sync::{broadcast::{self, error::RecvError}, mpsc, Mutex, Notify},
+    task::JoinHandle,
+};
+use tracing::{error, info, warn, Level};
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
+
+mod boards;
+mod effort;
+#[cfg(feature = "local-worker")]
+mod local_worker;
+mod logs;
+mod png;
 
 type SharedState = Arc<Mutex<AppState>>;
 
+#[derive(Serialize)]
 struct AppState {
     hosts: Vec<String>,
+    /// named group of each host that has one, for jobs aimed at a subset
+    groups: HashMap<String, String>,
     job_name: String,
-    running: bool,
+    job_id: u64,
+    /// only flipped with compare-and-set, so of two racing starts (or two
+    /// racing finishes) exactly one wins
+    running: AtomicBool,
+    #[serde(skip)]
+    stop: Arc<Notify>,
+    /// tells the server to stop taking requests and exit
+    #[serde(skip)]
+    shutdown: Arc<Notify>,
     batches: usize,
     batch_size: usize,
+    /// how batch sizes change over the job, from `batch_size`
+    schedule: BatchSchedule,
+    dispatched: usize,
     completed: usize,
-    keyboards: Vec<Keyboard>
+    /// batch numbers of the current job whose results are in, so one that
+    /// comes back twice only counts once
+    acked: HashSet<usize>,
+    /// best score and the ids given to the kept keyboards of each batch that's
+    /// in, so of two results for a batch the better one stays
+    batch_results: HashMap<usize, (Option<f32>, Range<u64>)>,
+    /// batches of the current job that came back without any keyboards
+    empty_batches: usize,
+    /// batches waiting to be handed out again, either empty ones (see
+    /// `redispatch_empty`) or ones a worker never started (see `init_timeout`)
+    requeued: Vec<usize>,
+    /// batches that have been requeued once already, so a worker that never
+    /// produces anything can't keep one going forever
+    retried: HashSet<usize>,
+    /// hosts whose current batch was restarted from the dashboard, until
+    /// their task gets to it
+    restarts: HashSet<String>,
+    min_completion: f32,
+    /// how long the current job may run, kept so a restart gets it too
+    max_runtime: Option<Duration>,
+    /// where the current job's summary gets posted when it finishes
+    webhook: Option<String>,
+    /// what each batch's seed is derived from, none leaves workers to pick
+    seed: Option<u64>,
+    /// the promoted keyboard the current job's workers start from
+    seed_layout: Option<Vec<Key>>,
+    #[serde(rename = "started_secs_ago", serialize_with = "secs_ago")]
+    started: Option<Instant>,
+    /// when the running job gets stopped regardless of progress
+    #[serde(rename = "deadline_secs_left", serialize_with = "secs_left")]
+    deadline: Option<Instant>,
+    /// when a worker last sent back a finished batch, or the job started if
+    /// none has yet
+    #[serde(rename = "last_result_secs_ago", serialize_with = "secs_ago")]
+    last_result: Option<Instant>,
+    pinned: Vec<(usize, char)>,
+    /// the only positions the current job may change, any if empty
+    mutable: Vec<usize>,
+    /// keys clicked on the best keyboard, the pinned keys the next job starts
+    /// with unless they're edited first
+    locks: Vec<(usize, char)>,
+    tags: Vec<String>,
+    /// physical board the qmk export targets, empty for the generic layout
+    board: String,
+    config: Config,
+    claims: HashMap<String, usize>,
+    /// batches each host holds right now
+    inflight: HashMap<String, usize>,
+    /// keyboards done and batch size of the batch each host last said it was
+    /// working on
+    batch_progress: HashMap<String, (usize, usize)>,
+    /// hosts whose last request failed
+    unhealthy: HashSet<String>,
+    /// most batches each host may hold at once, `DEFAULT_INFLIGHT` if unset
+    inflight_limits: HashMap<String, usize>,
+    #[serde(skip)]
+    sockets: HashMap<String, WorkerSocket>,
+    /// shared by every worker task so connections to a worker are kept alive
+    /// from one poll to the next
+    #[serde(skip)]
+    client: reqwest::Client,
+    /// where worker tasks send finished batches, see `run_ingest`
+    #[serde(skip)]
+    results: mpsc::UnboundedSender<Ingest>,
+    /// every log line as it's written, for `/logs`
+    #[serde(skip)]
+    logs: broadcast::Sender<logs::LogLine>,
+    #[serde(skip)]
+    tasks: Vec<JoinHandle<()>>,
+    /// keyboards rejected from each host for not being a permutation of
+    /// `alphabet` or not having a finite score
+    invalid: HashMap<String, usize>,
+    /// every glyph, either layer, on the first keyboard each host sent this
+    /// job
+    glyph_sets: HashMap<String, BTreeSet<char>>,
+    /// sum and count of the scores of every valid keyboard each host sent this
+    /// job, trimmed ones included
+    host_scores: HashMap<String, (f64, usize)>,
+    /// how each host has done this job, for its history entry
+    host_stats: HashMap<String, HostStats>,
+    /// when each batch of the current job that's out was last handed out
+    #[serde(skip)]
+    dispatched_at: HashMap<usize, Instant>,
+    keyboards: Vec<Keyboard>,
+    /// copies of the best `leader_slots` keyboards, best first, kept up to
+    /// date as they come in so pages don't have to sort them all
+    #[serde(skip)]
+    leaders: Vec<Keyboard>,
+    /// copies of keyboards picked out by hand, never trimmed or reset
+    favorites: Vec<Keyboard>,
+    /// best score after each batch of the current job came in, by batches
+    /// completed
+    convergence: Vec<(usize, f32)>,
+    /// the best keyboard of the current job before the latest one beat it
+    previous_best: Option<Keyboard>,
+    /// id for the next keyboard in, never reused so links outlive their job
+    next_keyboard_id: u64,
+    history: Vec<JobRecord>,
+    /// dispatch timelines of the last `EVENT_LOG_JOBS` jobs, by job id
+    events: HashMap<u64, Vec<JobEvent>>,
 }
 
 impl Default for AppState {
     fn default() -> Self {
         Self {
             hosts: vec![],
+            groups: HashMap::new(),
             job_name: "".into(),
-            running: false,
+            job_id: 0,
+            running: AtomicBool::new(false),
+            stop: Arc::new(Notify::new()),
+            shutdown: Arc::new(Notify::new()),
             batches: 0,
             batch_size: 0,
+            schedule: BatchSchedule::Constant,
+            dispatched: 0,
             completed: 0,
-            keyboards: vec![]
+            acked: HashSet::new(),
+            batch_results: HashMap::new(),
+            empty_batches: 0,
+            requeued: vec![],
+            retried: HashSet::new(),
+            restarts: HashSet::new(),
+            min_completion: 1.0,
+            max_runtime: None,
+            webhook: None,
+            seed: None,
+            seed_layout: None,
+            started: None,
+            deadline: None,
+            last_result: None,
+            pinned: vec![],
+            mutable: vec![],
+            locks: vec![],
+            tags: vec![],
+            board: "".into(),
+            config: Config::default(),
+            claims: HashMap::new(),
+            inflight: HashMap::new(),
+            batch_progress: HashMap::new(),
+            unhealthy: HashSet::new(),
+            inflight_limits: HashMap::new(),
+            sockets: HashMap::new(),
+            client: reqwest::Client::new(),
+            // goes nowhere until `main` connects it to `run_ingest`
+            results: mpsc::unbounded_channel().0,
+            logs: broadcast::channel(logs::BUFFER).0,
+            tasks: vec![],
+            invalid: HashMap::new(),
+            glyph_sets: HashMap::new(),
+            host_scores: HashMap::new(),
+            keyboards: vec![],
+            favorites: vec![],
+            convergence: vec![],
+            previous_best: None,
+            leaders: vec![],
+            host_stats: HashMap::new(),
+            dispatched_at: HashMap::new(),
+            next_keyboard_id: 0,
+            history: vec![],
+            events: HashMap::new(),
+        }
+    }
+}
+
+/// settings read from the environment once at startup
+#[derive(Serialize)]
+struct Config {
+    /// `BIND_ADDR`
+    bind_addr: SocketAddr,
+    /// `POLL_INTERVAL_MS`, how long to wait before asking a busy worker again
+    poll_interval: Duration,
+    /// `REQUEST_TIMEOUT_SECS`, for each request to a worker
+    request_timeout: Duration,
+    /// `PROBE_INTERVAL_SECS`, how often an unreachable worker is tried again
+    probe_interval: Duration,
+    /// `MAX_KEYBOARDS`, results kept per job, the worst are dropped past it
+    max_keyboards: usize,
+    /// `OUTPUT_DIR`, where each finished job's best keyboard gets saved
+    output_dir: Option<PathBuf>,
+    /// `SEED_FILE`, the keyboard `/promote-seed/:id` writes and a job can
+    /// start its workers from
+    seed_file: PathBuf,
+    /// `HOSTS_FILE`, where the workers added, their groups and inflight
+    /// limits are kept between runs
+    hosts_file: PathBuf,
+    /// `AUTH_TOKEN`, required by the admin endpoints, which are off without it
+    #[serde(skip)]
+    auth_token: Option<String>,
+    /// `WORKER_ENCODING`
+    encoding: WorkerEncoding,
+    /// `DISPATCH_POLICY`
+    policy: DispatchPolicy,
+    /// `SCORE_DIRECTION`
+    direction: ScoreDirection,
+    /// `MIN_DISPATCH_INTERVAL_MS`, least time a host waits between claiming
+    /// one batch and the next
+    min_dispatch_interval: Duration,
+    /// `ALPHABET`, the `upper` glyphs every keyboard must use exactly once,
+    /// sorted
+    alphabet: Vec<char>,
+    /// `ALPHABET` as given, the layout positions a job doesn't let move have
+    /// to keep
+    start_layout: Vec<char>,
+    /// `HTMX_SRC`, where pages load htmx from. the bundled copy unless set,
+    /// `HTMX_CDN` for instance
+    htmx_src: String,
+    /// `REDISPATCH_EMPTY`, whether a batch that comes back empty is handed
+    /// out once more before it counts
+    redispatch_empty: bool,
+    /// `BATCH_LOG_EVERY`, only every nth batch's dispatch and completion get
+    /// logged, 0 for none. failures are always logged.
+    batch_log_every: usize,
+    /// `STALE_AFTER_SECS`, how long a running job can go without a finished
+    /// batch before the panel flags it
+    stale_after: Duration,
+    /// `COMPLETION_WEBHOOK`, where each finished job's summary is posted
+    /// unless the job names its own
+    completion_webhook: Option<String>,
+    /// `PARSE_RETRY_MS`, wait before asking again after a worker's update
+    /// couldn't be read, doubling with each failure in a row up to
+    /// `probe_interval`
+    parse_retry: Duration,
+    /// `INIT_TIMEOUT_SECS`, how long a worker can keep answering `Init` after
+    /// being sent a batch before the batch is given to someone else
+    init_timeout: Duration,
+    /// `MAX_RENDERED`, most rows any one list on the dashboard gets, past
+    /// that it says how many were left out
+    max_rendered: usize,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            bind_addr: SocketAddr::from(([127, 0, 0, 1], 8000)),
+            poll_interval: Duration::from_secs(5),
+            request_timeout: Duration::from_secs(30),
+            probe_interval: Duration::from_secs(10),
+            max_keyboards: 1_000_000,
+            output_dir: None,
+            seed_file: PathBuf::from("seed.json"),
+            hosts_file: PathBuf::from("hosts.json"),
+            auth_token: None,
+            encoding: WorkerEncoding::Json,
+            policy: DispatchPolicy::Greedy,
+            direction: ScoreDirection::Minimize,
+            min_dispatch_interval: Duration::ZERO,
+            alphabet: sorted_alphabet(DEFAULT_ALPHABET),
+            start_layout: DEFAULT_ALPHABET.chars().collect(),
+            htmx_src: HTMX_BUNDLED.into(),
+            redispatch_empty: false,
+            batch_log_every: 100,
+            stale_after: Duration::from_secs(60),
+            completion_webhook: None,
+            parse_retry: Duration::from_secs(1),
+            init_timeout: Duration::from_secs(60),
+            max_rendered: 200,
+        }
+    }
+}
+
+impl Config {
+    fn from_env() -> Result<Self, String> {
+        let defaults = Self::default();
+        let config = Self {
+            bind_addr: env_or("BIND_ADDR", defaults.bind_addr)?,
+            poll_interval: env_or("POLL_INTERVAL_MS", defaults.poll_interval.as_millis() as u64)
+                .map(Duration::from_millis)?,
+            request_timeout: env_or("REQUEST_TIMEOUT_SECS", defaults.request_timeout.as_secs())
+                .map(Duration::from_secs)?,
+            probe_interval: env_or("PROBE_INTERVAL_SECS", defaults.probe_interval.as_secs())
+                .map(Duration::from_secs)?,
+            max_keyboards: env_or("MAX_KEYBOARDS", defaults.max_keyboards)?,
+            output_dir: std::env::var_os("OUTPUT_DIR").map(PathBuf::from),
+            seed_file: std::env::var_os("SEED_FILE").map_or(defaults.seed_file, PathBuf::from),
+            hosts_file: std::env::var_os("HOSTS_FILE").map_or(defaults.hosts_file, PathBuf::from),
+            auth_token: std::env::var("AUTH_TOKEN").ok().filter(|t| !t.is_empty()),
+            encoding: WorkerEncoding::from_env(),
+            policy: DispatchPolicy::from_env(),
+            direction: ScoreDirection::from_env(),
+            min_dispatch_interval: env_or("MIN_DISPATCH_INTERVAL_MS", 0).map(Duration::from_millis)?,
+            alphabet: match std::env::var("ALPHABET") {
+                Ok(glyphs) => sorted_alphabet(&glyphs),
+                Err(_) => defaults.alphabet,
+            },
+            start_layout: match std::env::var("ALPHABET") {
+                Ok(glyphs) => glyphs.chars().collect(),
+                Err(_) => defaults.start_layout,
+            },
+            htmx_src: std::env::var("HTMX_SRC").unwrap_or(defaults.htmx_src),
+            redispatch_empty: env_or("REDISPATCH_EMPTY", defaults.redispatch_empty)?,
+            batch_log_every: env_or("BATCH_LOG_EVERY", defaults.batch_log_every)?,
+            stale_after: env_or("STALE_AFTER_SECS", defaults.stale_after.as_secs()).map(Duration::from_secs)?,
+            completion_webhook: match std::env::var("COMPLETION_WEBHOOK") {
+                Ok(url) if url.trim().is_empty() => None,
+                Ok(url) => Some(parse_webhook(&url)?),
+                Err(_) => None,
+            },
+            parse_retry: env_or("PARSE_RETRY_MS", defaults.parse_retry.as_millis() as u64)
+                .map(Duration::from_millis)?,
+            init_timeout: env_or("INIT_TIMEOUT_SECS", defaults.init_timeout.as_secs()).map(Duration::from_secs)?,
+            max_rendered: env_or("MAX_RENDERED", defaults.max_rendered)?,
+        };
+        if config.poll_interval.is_zero() {
+            return Err("POLL_INTERVAL_MS must be greater than zero".into())
+        }
+        if config.request_timeout.is_zero() {
+            return Err("REQUEST_TIMEOUT_SECS must be greater than zero".into())
+        }
+        if config.probe_interval.is_zero() {
+            return Err("PROBE_INTERVAL_SECS must be greater than zero".into())
+        }
+        if config.parse_retry.is_zero() {
+            return Err("PARSE_RETRY_MS must be greater than zero".into())
+        }
+        if config.init_timeout.is_zero() {
+            return Err("INIT_TIMEOUT_SECS must be greater than zero".into())
+        }
+        if config.stale_after.is_zero() {
+            return Err("STALE_AFTER_SECS must be greater than zero".into())
+        }
+        if config.max_rendered == 0 {
+            return Err("MAX_RENDERED must be greater than zero".into())
+        }
+        if config.max_keyboards == 0 {
+            return Err("MAX_KEYBOARDS must be greater than zero".into())
+        }
+        if config.alphabet.len() != 47 || config.alphabet.windows(2).any(|w| w[0] == w[1]) {
+            return Err("ALPHABET must be 47 distinct glyphs".into())
+        }
+        Ok(config)
+    }
+
+    /// scripts only from us and wherever htmx comes from, none inline. inline
+    /// styles stay allowed since the pages are built out of them, and htmx
+    /// adds its own.
+    fn content_security_policy(&self) -> String {
+        let htmx_origin = ["https://", "http://"]
+            .iter()
+            .find_map(|scheme| {
+                let rest = self.htmx_src.strip_prefix(scheme)?;
+                Some(format!(" {}{}", scheme, rest.split('/').next().unwrap_or(rest)))
+            })
+            .unwrap_or_default();
+        format!(
+            "default-src 'self'; script-src 'self'{}; style-src 'self' 'unsafe-inline'; \
+             img-src 'self' data: https://htmx.org; frame-ancestors 'none'; form-action 'self'",
+            htmx_origin,
+        )
+    }
+
+    fn log(&self) {
+        info!(
+            bind_addr = %self.bind_addr,
+            poll_interval = ?self.poll_interval,
+            request_timeout = ?self.request_timeout,
+            probe_interval = ?self.probe_interval,
+            max_keyboards = self.max_keyboards,
+            output_dir = ?self.output_dir,
+            seed_file = %self.seed_file.display(),
+            hosts_file = %self.hosts_file.display(),
+            auth_token = if self.auth_token.is_some() { "set" } else { "unset" },
+            min_dispatch_interval = ?self.min_dispatch_interval,
+            alphabet = %self.alphabet.iter().collect::<String>(),
+            htmx_src = %self.htmx_src,
+            redispatch_empty = self.redispatch_empty,
+            batch_log_every = self.batch_log_every,
+            stale_after = ?self.stale_after,
+            completion_webhook = ?self.completion_webhook,
+            parse_retry = ?self.parse_retry,
+            init_timeout = ?self.init_timeout,
+            max_rendered = self.max_rendered,
+            "config loaded",
+        );
+    }
+}
+
+/// `name` parsed, or `default` if it isn't set
+fn env_or<T: std::str::FromStr>(name: &str, default: T) -> Result<T, String> {
+    match std::env::var(name) {
+        Ok(value) => value.trim().parse().map_err(|_| format!("{} \"{}\" isn't valid", name, value)),
+        Err(_) => Ok(default),
+    }
+}
+
+impl AppState {
+    fn inflight_limit(&self, host: &str) -> usize {
+        self.inflight_limits.get(host).copied().unwrap_or(DEFAULT_INFLIGHT)
+    }
+
+    /// whether `batch` is one of the sampled ones that get logged
+    fn logs_batch(&self, batch: usize) -> bool {
+        self.config.batch_log_every != 0 && batch.is_multiple_of(self.config.batch_log_every)
+    }
+
+    /// whether `job_id` is the job that's running right now
+    fn is_current(&self, job_id: u64) -> bool {
+        self.is_running() && self.job_id == job_id
+    }
+
+    /// the keyboard with `id` and its current rank, favorites included
+    fn keyboard(&self, id: u64) -> Option<(usize, &Keyboard)> {
+        if let Some(found) = self.leaders.iter().position(|k| k.id == id) {
+            return Some((found, &self.leaders[found]))
+        }
+        let keyboard = self.keyboards.iter().chain(&self.favorites).find(|k| k.id == id)?;
+        let direction = self.config.direction;
+        let rank = self.keyboards.iter().filter(|k| rank_cmp(direction, k, keyboard) == Ordering::Less).count();
+        Some((rank, keyboard))
+    }
+
+    fn best(&self) -> Option<&Keyboard> {
+        self.leaders.first()
+    }
+
+    /// how many keyboards `leaders` holds, enough for any list a page renders
+    fn leader_slots(&self) -> usize {
+        self.config.max_rendered.max(LEADERBOARD_SIZE)
+    }
+
+    /// recomputes `leaders` after keyboards were taken out
+    fn rebuild_leaders(&mut self) {
+        let slots = self.leader_slots();
+        self.leaders = ranked(&self.keyboards, self.config.direction).into_iter().take(slots).cloned().collect();
+    }
+
+    /// gives the keyboard an id and adds it to the results
+    fn insert_keyboard(&mut self, mut keyboard: Keyboard) -> u64 {
+        keyboard.id = self.next_keyboard_id;
+        self.next_keyboard_id += 1;
+        let direction = self.config.direction;
+        let at = self.leaders.partition_point(|k| rank_cmp(direction, k, &keyboard) == Ordering::Less);
+        if at < self.leader_slots() {
+            self.leaders.insert(at, keyboard.clone());
+            self.leaders.truncate(self.leader_slots());
+        }
+        self.keyboards.push(keyboard);
+        self.next_keyboard_id - 1
+    }
+
+    /// whether every position the job doesn't let move, pinned ones aside,
+    /// still has its glyph from where workers started, the seed layout if the
+    /// job has one and `start_layout` otherwise
+    fn keeps_fixed(&self, keyboard: &Keyboard) -> bool {
+        let start = |i: usize| match &self.seed_layout {
+            Some(seed) => seed[i].upper,
+            None => self.config.start_layout[i],
+        };
+        self.mutable.is_empty() || (0..47)
+            .filter(|i| !self.mutable.contains(i) && !self.pinned.iter().any(|(p, _)| p == i))
+            .all(|i| keyboard.keys[i].upper == start(i))
+    }
+
+    /// drops the worst keyboards past `max_keyboards`
+    fn trim_keyboards(&mut self) {
+        let max = self.config.max_keyboards;
+        if self.keyboards.len() > max {
+            let direction = self.config.direction;
+            self.keyboards.select_nth_unstable_by(max, |a, b| rank_cmp(direction, a, b));
+            self.keyboards.truncate(max);
+            self.leaders.truncate(max);
+        }
+    }
+
+    fn is_running(&self) -> bool {
+        self.running.load(atomic::Ordering::SeqCst)
+    }
+
+    /// claims the running flag, false if a job already holds it
+    fn try_start(&self) -> bool {
+        self.running
+            .compare_exchange(false, true, atomic::Ordering::SeqCst, atomic::Ordering::SeqCst)
+            .is_ok()
+    }
+
+    /// releases the running flag, false if it was already released
+    fn try_stop(&self) -> bool {
+        self.running
+            .compare_exchange(true, false, atomic::Ordering::SeqCst, atomic::Ordering::SeqCst)
+            .is_ok()
+    }
+
+    /// batches that have to come back before the job counts as done
+    fn required(&self) -> usize {
+        ((self.batches as f32 * self.min_completion).ceil() as usize).clamp(1, self.batches.max(1))
+    }
+}
+
+/// a worker connected over `/ws/worker`. batches and cancellations are pushed
+/// down `tx`, and whatever the worker reports comes back on `updates`.
+#[derive(Clone)]
+struct WorkerSocket {
+    tx: mpsc::UnboundedSender<ControlMsg>,
+    updates: Arc<Mutex<mpsc::UnboundedReceiver<UpdateResp>>>,
+}
+
+/// what the coordinator pushes to socket workers, the counterpart of `UpdateResp`
+#[derive(Serialize)]
+enum ControlMsg {
+    Batch(BatchReq),
+    Cancel,
+}
+
+/// what a worker task needs to know about the job it was spawned for
+#[derive(Clone)]
+struct JobHandle {
+    id: u64,
+    encoding: WorkerEncoding,
+    poll_interval: Duration,
+    probe_interval: Duration,
+    parse_retry: Duration,
+    init_timeout: Duration,
+    min_dispatch_interval: Duration,
+    client: reqwest::Client,
+    results: mpsc::UnboundedSender<Ingest>,
+    stop: Arc<Notify>,
+}
+
+impl JobHandle {
+    /// sleeps for `duration`, waking early if the job stops
+    async fn pause(&self, duration: Duration) {
+        tokio::select! {
+            _ = tokio::time::sleep(duration) => {},
+            _ = self.stop.notified() => {},
+        }
+    }
+
+    /// frees the host's slot for its next batch and queues the results for
+    /// `run_ingest`, leaving the heavy lifting out of the worker's task
+    fn hand_in(&self, state: &mut AppState, host: &str, batch: usize, keyboards: Vec<Keyboard>) {
+        if let Some(inflight) = state.inflight.get_mut(host) {
+            *inflight = inflight.saturating_sub(1);
+        }
+        state.batch_progress.remove(host);
+        // the batch made it back before anyone restarted it
+        state.restarts.remove(host);
+        if let Some(at) = state.dispatched_at.remove(&batch) {
+            let stats = state.host_stats.entry(host.to_string()).or_default();
+            stats.batches += 1;
+            stats.busy += at.elapsed();
+        }
+        let ingest = Ingest { job_id: self.id, host: host.to_string(), batch, keyboards };
+        if self.results.send(ingest).is_err() {
+            error!(host, batch, "results dropped, nothing is ingesting them");
+        }
+    }
+
+    /// runs `request` unless the job stops first, in which case it's dropped
+    /// mid-flight and `None` comes back
+    async fn unless_stopped<T>(&self, request: impl std::future::Future<Output = T>) -> Option<T> {
+        tokio::select! {
+            out = request => Some(out),
+            _ = self.stop.notified() => None,
+        }
+    }
+
+    /// holds off until `min_dispatch_interval` has passed since `last_claim`,
+    /// so a worker that answers instantly doesn't keep the loop spinning
+    async fn throttle(&self, last_claim: Option<Instant>) {
+        let Some(last_claim) = last_claim else { return };
+        let wait = self.min_dispatch_interval.saturating_sub(last_claim.elapsed());
+        if !wait.is_zero() {
+            self.pause(wait).await;
+        }
+    }
+}
+
+/// a finished batch on its way to `run_ingest`
+struct Ingest {
+    job_id: u64,
+    host: String,
+    batch: usize,
+    keyboards: Vec<Keyboard>,
+}
+
+/// summary of a finished job
+#[derive(Serialize)]
+struct JobRecord {
+    job_id: u64,
+    job_name: String,
+    batch_size: usize,
+    batches: usize,
+    completed: usize,
+    best_score: Option<f32>,
+    best: Option<Keyboard>,
+    tags: Vec<String>,
+    seed: Option<u64>,
+    outcome: JobOutcome,
+    runtime_secs: f64,
+    /// by host name
+    hosts: Vec<HostRecord>,
+}
+
+/// what a host did during a job, tallied as it runs
+#[derive(Clone, Default, Serialize)]
+struct HostStats {
+    /// batches handed back, late duplicates included
+    batches: usize,
+    /// keyboards thrown out at ingestion
+    rejected: usize,
+    /// times it stopped answering or never started a batch
+    failures: usize,
+    /// time from handing out each of `batches` to getting it back
+    busy: Duration,
+}
+
+/// a host's part in a finished job
+#[derive(Serialize)]
+struct HostRecord {
+    host: String,
+    batches: usize,
+    keyboards: usize,
+    rejected: usize,
+    failures: usize,
+    avg_batch_secs: Option<f64>,
+}
+
+/// why a job stopped
+#[derive(Clone, Copy, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum JobOutcome {
+    Complete,
+    TimeLimit,
+    Stopped,
+}
+
+impl JobOutcome {
+    fn label(self) -> &'static str {
+        match self {
+            Self::Complete => "complete",
+            Self::TimeLimit => "stopped: time limit",
+            Self::Stopped => "stopped",
+        }
+    }
+}
+
+/// something that happened during a job, for `/export/events/:job`
+#[derive(Serialize)]
+struct JobEvent {
+    /// milliseconds since the unix epoch
+    at_ms: u128,
+    #[serde(flatten)]
+    kind: EventKind,
+}
+
+#[derive(Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+enum EventKind {
+    Start { hosts: Vec<String> },
+    Dispatch { host: String, batch: usize },
+    /// a worker lost its batch, so it was sent the same one again
+    Redispatch { host: String, batch: usize },
+    /// a worker never started its batch, so it was released for another
+    Stuck { host: String, batch: usize },
+    /// someone gave up on a worker's batch from the dashboard
+    Restart { host: String, batch: usize },
+    /// a worker was taken out of the pool mid-job
+    Removed { host: String },
+    Complete { host: String, batch: usize, keyboards: usize, rejected: usize },
+    Unreachable { host: String, error: String },
+    Recovered { host: String },
+    Finish { outcome: &'static str },
+    ForcedIdle,
+    /// the log hit `MAX_JOB_EVENTS`, nothing after this was kept
+    Truncated,
+}
+
+/// wire format requested from workers for `/update` responses. workers that
+/// don't understand msgpack keep answering in json, so responses are always
+/// decoded by their content type.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum WorkerEncoding {
+    Json,
+    MsgPack,
+}
+
+impl WorkerEncoding {
+    fn from_env() -> Self {
+        match std::env::var("WORKER_ENCODING").as_deref() {
+            Ok("msgpack") => Self::MsgPack,
+            _ => Self::Json,
+        }
+    }
+
+    fn accept(self) -> &'static str {
+        match self {
+            Self::Json => "application/json",
+            Self::MsgPack => "application/msgpack, application/json;q=0.9",
+        }
+    }
+}
+
+/// how batches are shared out between hosts. `Greedy` lets whichever host is
+/// free grab the next batch, `Fair` makes hosts take turns so similar workers
+/// end up with similar shares, at the cost of idling fast ones.
+#[derive(Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+enum DispatchPolicy {
+    Greedy,
+    Fair,
+}
+
+impl DispatchPolicy {
+    fn from_env() -> Self {
+        match std::env::var("DISPATCH_POLICY").as_deref() {
+            Ok("fair") => Self::Fair,
+            _ => Self::Greedy,
+        }
+    }
+}
+
+/// whether workers' scores are costs to push down or rewards to push up
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum ScoreDirection {
+    Minimize,
+    Maximize,
+}
+
+impl ScoreDirection {
+    fn from_env() -> Self {
+        match std::env::var("SCORE_DIRECTION").as_deref() {
+            Ok("maximize") => Self::Maximize,
+            _ => Self::Minimize,
         }
     }
+
+    /// orders better scores first
+    fn cmp(self, a: f32, b: f32) -> Ordering {
+        let ord = a.total_cmp(&b);
+        match self {
+            Self::Minimize => ord,
+            Self::Maximize => ord.reverse(),
+        }
+    }
+
+    /// whether `score` is at least as good as `threshold`
+    fn meets(self, score: f32, threshold: f32) -> bool {
+        self.cmp(score, threshold) != Ordering::Greater
+    }
+}
+
+enum Claim {
+    Batch(usize),
+    /// another host is due a batch first
+    Wait,
+    /// nothing left to hand out, or the job is over
+    Done,
 }
 
-#[allow(dead_code)]
+/// what a worker reports about its current batch. progress fields are
+/// optional so older and newer workers can skip them, and unknown fields are
+/// ignored.
 #[derive(Deserialize)]
 enum UpdateResp {
     Init,
     InProgress {
+        #[serde(default)]
         batch_size: usize,
+        #[serde(default)]
         completed: usize,
     },
     BatchComplete {
@@ -62,25 +843,96 @@ struct BatchReq {
     device_name: String,
     batch_size: usize,
     batch_number: usize,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pinned: Vec<(usize, char)>,
+    /// positions the worker may change, all of them if left out
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    mutable_positions: Vec<usize>,
+    /// the job's seed plus `batch_number`, for workers to make the batch
+    /// reproducible with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed: Option<u64>,
+    /// a keyboard to improve on rather than starting from scratch
+    #[serde(skip_serializing_if = "Option::is_none")]
+    seed_layout: Option<Vec<Key>>,
 }
 
-#[derive(Deserialize, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 struct Keyboard {
+    /// assigned by the coordinator as keyboards come in, whatever a worker
+    /// sends here is overwritten
+    #[serde(default)]
+    id: u64,
     score: f32,
     #[serde(with = "BigArray")]
     keys: [Key; 47],
+    /// anything else the worker measured, by name, ranked the same way as
+    /// `score`
+    #[serde(default)]
+    metrics: BTreeMap<String, f32>,
+    /// how many rounds of the worker's search this keyboard came out of
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    generation: Option<u32>,
+    /// id of the keyboard it was derived from, as the worker reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    parent_id: Option<u64>,
 }
 
-#[allow(dead_code)]
-#[derive(Deserialize, Clone, Copy)]
+#[derive(Serialize, Deserialize, Clone, Copy)]
 struct Key {
+    #[serde(deserialize_with = "loose_char")]
     lower: char,
+    #[serde(deserialize_with = "loose_char")]
     upper: char,
 }
 
+/// a glyph sent as a char, a one character string or a unicode code point,
+/// since not every worker's language has a char type
+fn loose_char<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<char, D::Error> {
+    struct LooseChar;
+
+    impl serde::de::Visitor<'_> for LooseChar {
+        type Value = char;
+
+        fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+            f.write_str("a single character or a unicode code point")
+        }
+
+        fn visit_char<E: serde::de::Error>(self, c: char) -> Result<char, E> {
+            Ok(c)
+        }
+
+        fn visit_str<E: serde::de::Error>(self, s: &str) -> Result<char, E> {
+            let mut chars = s.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Ok(c),
+                _ => Err(E::invalid_value(serde::de::Unexpected::Str(s), &self)),
+            }
+        }
+
+        fn visit_u64<E: serde::de::Error>(self, n: u64) -> Result<char, E> {
+            u32::try_from(n)
+                .ok()
+                .and_then(char::from_u32)
+                .ok_or_else(|| E::invalid_value(serde::de::Unexpected::Unsigned(n), &self))
+        }
+
+        fn visit_i64<E: serde::de::Error>(self, n: i64) -> Result<char, E> {
+            match u64::try_from(n) {
+                Ok(n) => self.visit_u64(n),
+                Err(_) => Err(E::invalid_value(serde::de::Unexpected::Signed(n), &self)),
+            }
+        }
+    }
+
+    deserializer.deserialize_any(LooseChar)
+}
+
 #[derive(Deserialize)]
 struct AddServerReq {
-    host: String
+    host: String,
+    #[serde(default)]
+    group: String,
 }
 
 #[derive(Deserialize)]
@@ -88,30 +940,446 @@ struct StartJobReq {
     job_name: String,
     batch_size: usize,
     batches: usize,
+    #[serde(default)]
+    pinned: String,
+    #[serde(default)]
+    tags: String,
+    #[serde(default)]
+    min_completion_fraction: String,
+    #[serde(default)]
+    board: String,
+    #[serde(default)]
+    max_runtime_secs: String,
+    #[serde(default)]
+    group: String,
+    /// overrides `completion_webhook` for this job
+    #[serde(default)]
+    completion_webhook: String,
+    #[serde(default)]
+    seed: String,
+    /// space or comma separated, every position if empty
+    #[serde(default)]
+    mutable_positions: String,
+    /// "on" to start workers from the promoted seed keyboard
+    #[serde(default)]
+    use_seed: String,
+    /// see `parse_schedule`, every batch is `batch_size` if empty
+    #[serde(default)]
+    batch_schedule: String,
+}
+
+/// the size of each batch of a job
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum BatchSchedule {
+    /// every batch is `batch_size`
+    Constant,
+    /// each batch is this many times the size of the one before, starting
+    /// from `batch_size`
+    Growth(f32),
+    /// one size per batch, in order
+    Sizes(Vec<usize>),
+}
+
+impl BatchSchedule {
+    /// size of batch `batch_number` for a job of batches of `batch_size`
+    fn size(&self, batch_size: usize, batch_number: usize) -> usize {
+        match self {
+            Self::Constant => batch_size,
+            Self::Growth(factor) => {
+                let size = batch_size as f64 * (*factor as f64).powf(batch_number as f64);
+                (size.round() as usize).max(1)
+            },
+            Self::Sizes(sizes) => sizes.get(batch_number).copied().unwrap_or(batch_size),
+        }
+    }
+
+    /// keyboards asked for over `batches` batches
+    fn total(&self, batch_size: usize, batches: usize) -> usize {
+        (0..batches).fold(0, |total: usize, n| total.saturating_add(self.size(batch_size, n)))
+    }
+
+    /// `total`, or `None` if it's too many to count. a growth size that
+    /// doesn't fit is `usize::MAX`, so it always overflows the sum.
+    fn checked_total(&self, batch_size: usize, batches: usize) -> Option<usize> {
+        (0..batches).try_fold(0, |total: usize, n| total.checked_add(self.size(batch_size, n)))
+    }
+
+    /// an error if the schedule doesn't say how big each of `batches` is
+    fn covers(&self, batches: usize) -> Result<(), String> {
+        match self {
+            Self::Sizes(sizes) if sizes.len() < batches => Err(format!(
+                "batch schedule lists {} sizes for {} batches",
+                sizes.len(),
+                batches,
+            )),
+            _ => Ok(()),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+struct ConvergenceReq {
+    alpha: Option<f32>,
+}
+
+#[derive(Deserialize)]
+struct SetBatchesReq {
+    batches: usize,
+}
+
+#[derive(Deserialize)]
+struct PageReq {
+    #[serde(default)]
+    offset: usize,
+    limit: Option<usize>,
+    /// decimal places scores are rounded to, all of them if unset
+    precision: Option<usize>,
+}
+
+#[derive(Deserialize)]
+struct CsvReq {
+    precision: Option<usize>,
+    /// whether the first line names the columns, yes if unset
+    header: Option<bool>,
+}
+
+#[derive(Deserialize)]
+struct SetInflightReq {
+    host: String,
+    limit: usize,
+}
+
+#[derive(Deserialize)]
+struct RemoveServerReq {
+    host: String,
+}
+
+#[derive(Deserialize)]
+struct RestartBatchReq {
+    host: String,
+}
+
+#[derive(Deserialize)]
+struct WorkerSocketReq {
+    host: String,
+}
+
+#[derive(Deserialize, Default)]
+struct UpdateReq {
+    min_score: Option<String>,
+    #[serde(default)]
+    labels: Labels,
+    /// metric to rank by, `score` if empty
+    #[serde(default)]
+    sort: String,
+    /// a finished job from the history to show instead of the current one
+    job: Option<u64>,
+    /// leaderboard rows, `LEADERBOARD_SIZE` if unset
+    top: Option<usize>,
+}
+
+/// what's printed on each key
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+enum Labels {
+    #[default]
+    Glyph,
+    /// the key's position in `Keyboard::keys`
+    Index,
+}
+
+#[derive(Deserialize)]
+struct ImportLayoutReq {
+    layout: String,
+    #[serde(default)]
+    score: String,
+}
+
+#[derive(Deserialize)]
+struct TokenReq {
+    token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct LogsReq {
+    /// least severe level sent, `info` if unset
+    level: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct ScoresReq {
+    #[serde(default)]
+    order: ScoreOrder,
+}
+
+#[derive(Deserialize, Clone, Copy, Default)]
+#[serde(rename_all = "lowercase")]
+enum ScoreOrder {
+    /// best first
+    #[default]
+    Ranked,
+    /// the order keyboards came in
+    Inserted,
+}
+
+#[derive(Deserialize)]
+struct ToggleLockReq {
+    id: u64,
+    pos: usize,
+}
+
+#[derive(Deserialize)]
+struct HistoryReq {
+    tag: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct PreviewDispatchReq {
+    batch_size: usize,
+    batches: usize,
+    #[serde(default)]
+    job_name: String,
+    #[serde(default)]
+    pinned: String,
+    #[serde(default)]
+    group: String,
+    #[serde(default)]
+    seed: String,
+    #[serde(default)]
+    mutable_positions: String,
+    #[serde(default)]
+    batch_schedule: String,
+}
+
+#[derive(Deserialize)]
+struct ValidateHostReq {
+    #[serde(default)]
+    host: String,
+}
+
+#[derive(Deserialize)]
+struct ValidateJobReq {
+    #[serde(default)]
+    batch_size: String,
+    #[serde(default)]
+    batches: String,
+    #[serde(default)]
+    group: String,
+    #[serde(default)]
+    batch_schedule: String,
 }
 
+/// total keyboards past which a job gets flagged as very large
+const LARGE_JOB_KEYBOARDS: usize = 10_000_000;
+
+/// keyboards a second assumed of a worker that hasn't finished a job yet
+const DEFAULT_KEYBOARDS_PER_SEC: f64 = 50.0;
+
+/// estimated runtime past which starting a job comes with a warning
+const LONG_JOB: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// us shift pairs for keys that aren't letters, (unshifted, shifted)
+const SHIFT_PAIRS: [(char, char); 21] = [
+    ('`', '~'), ('1', '!'), ('2', '@'), ('3', '#'), ('4', '$'), ('5', '%'),
+    ('6', '^'), ('7', '&'), ('8', '*'), ('9', '('), ('0', ')'), ('-', '_'),
+    ('=', '+'), ('[', '{'), (']', '}'), ('\\', '|'), (';', ':'), ('\'', '"'),
+    (',', '<'), ('.', '>'), ('/', '?'),
+];
+
+/// keyboards listed under the best one in the status panel
+const LEADERBOARD_SIZE: usize = 10;
+
+/// wait before a host that isn't due a batch asks again
+const CLAIM_RETRY_INTERVAL: Duration = Duration::from_millis(500);
+/// bytes of an unreadable worker response that make it into the log
+const MAX_LOGGED_BODY: usize = 200;
+/// longest shutdown waits on stopped workers and saves
+const SHUTDOWN_GRACE: Duration = Duration::from_secs(5);
+
+/// host name that routes batches to the in-process worker
+const LOCAL_HOST: &str = "local://";
+/// how long the add-worker form waits on a host before calling it unreachable
+const VALIDATE_HOST_TIMEOUT: Duration = Duration::from_secs(3);
+/// version of the batch protocol spoken with workers, bumped whenever
+/// `BatchReq`, `UpdateResp` or `ControlMsg` change incompatibly
+const PROTOCOL_VERSION: u32 = 1;
+/// weight of the newest point in the smoothed convergence series
+const DEFAULT_EMA_ALPHA: f32 = 0.3;
+/// batches a host may hold at once until someone changes it
+const DEFAULT_INFLIGHT: usize = 1;
+/// keyboards per `/api/keyboards` page unless asked otherwise, and at most
+const DEFAULT_PAGE: usize = 50;
+const MAX_PAGE: usize = 500;
+/// past this an f32 has no more digits to show
+const MAX_PRECISION: usize = 9;
+/// largest `/export/json` body `/merge` takes, well past the default so a
+/// full result set fits
+const MAX_MERGE_BYTES: usize = 256 * 1024 * 1024;
+/// jobs whose event logs are kept
+const EVENT_LOG_JOBS: u64 = 20;
+/// events kept per job
+const MAX_JOB_EVENTS: usize = 10_000;
+/// ancestors `/lineage/:id` follows before it stops
+const MAX_LINEAGE: usize = 1_000;
+/// where the copy of htmx in `assets/htmx.min.js` is served, what pages load
+/// unless `HTMX_SRC` says otherwise
+const HTMX_BUNDLED: &str = "/static/htmx.js";
+/// where `assets/app.js` is served, the script behind the pages' buttons
+const APP_JS: &str = "/static/app.js";
+/// the same htmx build on the cdn, for `HTMX_SRC`. either is checked against
+/// the hash, keep all three in step with `assets/htmx.min.js`
+const HTMX_CDN: &str = "https://unpkg.com/htmx.org@1.9.3";
+const HTMX_CDN_INTEGRITY: &str = "sha384-lVb3Rd/Ca0AxaoZg5sACe8FJKF0tnUgR2Kd7ehUOG5GCcROv5uBIZsOqovBAcWua";
+/// every page's title until a job's progress takes over
+const PAGE_TITLE: &str = "keyboard generation nonsense";
+/// how often the dashboard asks for `/update` while a job runs, and otherwise
+const ACTIVE_POLL: &str = "every 5s";
+const IDLE_POLL: &str = "every 30s";
+/// htmx event a started job fires, so an idle dashboard speeds up right away
+const JOB_STARTED_EVENT: &str = "job-started";
+/// unshifted glyphs of a us qwerty board
+const DEFAULT_ALPHABET: &str = "`1234567890-=qwertyuiop[]\\asdfghjkl;'zxcvbnm,./";
+
+/// visual rows of the 47 key board, as ranges into `Keyboard::keys`
+const ROWS: [(usize, usize); 4] = [(0, 13), (13, 26), (26, 37), (37, 47)];
+/// the row in `ROWS` the fingers rest on, asdf... on a qwerty board
+const HOME_ROW: usize = 2;
+/// where each row starts on an ansi board, in key widths, for the tab, caps
+/// and shift keys that aren't part of the layout. the generic board's
+/// stagger, a board in `boards` brings its own.
+const ROW_OFFSETS: [f32; 4] = [0.0, 1.5, 1.75, 2.25];
+/// how wide a key is drawn on the page
+const KEY_EM: f32 = 2.5;
+/// same finger pairs outlined on a drawn keyboard, the most frequent ones.
+/// past a handful the whole board lights up.
+const SFB_HIGHLIGHTED: usize = 5;
+
 #[tokio::main]
 async fn main() {
     dotenv::dotenv().ok();
+    let (logs, _) = broadcast::channel(logs::BUFFER);
+    tracing_subscriber::registry()
+        .with(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with(tracing_subscriber::fmt::layer())
+        .with(logs::BroadcastLayer { tx: logs.clone() })
+        .init();
+
+    let config = match Config::from_env() {
+        Ok(config) => config,
+        Err(e) => {
+            error!("{}", e);
+            std::process::exit(1);
+        },
+    };
+    config.log();
+    let addr = config.bind_addr;
+    let client = reqwest::Client::builder()
+        .timeout(config.request_timeout)
+        .build()
+        .expect("http client");
+    let (results, incoming) = mpsc::unbounded_channel();
+    let pool = read_hosts(&config.hosts_file).await;
+    let state = Arc::new(Mutex::new(AppState {
+        hosts: pool.hosts,
+        groups: pool.groups,
+        inflight_limits: pool.inflight_limits,
+        client,
+        results,
+        logs,
+        config,
+        ..Default::default()
+    }));
+    tokio::spawn(run_ingest(state.clone(), incoming));
 
-    let state = Arc::new(Mutex::new(AppState::default()));
+    let admin = Router::new()
+        .route("/admin/force-idle", post(force_idle))
+        .route("/admin/compact", post(compact))
+        .route("/debug/state", get(debug_state))
+        .route("/logs", get(stream_logs))
+        .route("/shutdown", post(shutdown))
+        .route_layer(middleware::from_fn_with_state(state.clone(), require_auth));
 
     let router = Router::new()
         .route("/", get(root))
         .route("/update", get(update))
+        .route("/view", get(view))
+        .route("/view/status", get(view_status))
         .route("/add-server", post(add_server))
+        .route("/remove-server", post(remove_server))
         .route("/start-job", post(start_job))
-        .with_state(state);
+        .route("/set-inflight", post(set_inflight))
+        .route("/restart-batch", post(restart_batch))
+        .route("/set-batches", post(set_batches))
+        .route("/stop-job", post(stop_job))
+        .route("/stop-all", post(stop_all))
+        .route("/restart-job", post(restart_job))
+        .route("/reset", post(reset))
+        .route("/favorite/:id", post(favorite))
+        .route("/unfavorite/:id", post(unfavorite))
+        .route("/toggle-lock", post(toggle_lock))
+        .route("/promote-seed/:id", post(promote_seed))
+        .route("/validate-job", get(validate_job_params))
+        .route("/validate-host", get(validate_host))
+        .route("/preview-dispatch", get(preview_dispatch))
+        .route("/export/png", get(export_png))
+        .route("/export/qmk", get(export_qmk_best))
+        .route("/export/qmk/:id", get(export_qmk))
+        .route("/export/string/:id", get(export_string))
+        .route("/export/kle/:id", get(export_kle))
+        .route("/export/csv", get(export_csv))
+        .route("/export/json", get(export_json))
+        .route("/merge", post(merge).layer(DefaultBodyLimit::max(MAX_MERGE_BYTES)))
+        .route("/export/manifest/:job", get(export_manifest))
+        .route("/export/events/:job", get(export_events))
+        .route("/report/:job", get(report))
+        .route("/import-layout", post(import_layout))
+        .route("/api/glyphs", get(glyphs))
+        .route("/api/keyboards", get(keyboards_page))
+        .route("/api/scores", get(scores))
+        .route("/api/version", get(version))
+        .route("/history", get(history))
+        .route("/jobs", get(jobs))
+        .route("/convergence", get(convergence))
+        .route("/position-heatmap", get(position_heatmap))
+        .route("/hall-of-fame", get(hall_of_fame))
+        .route("/rescore/:id", get(rescore))
+        .route("/vs-baseline/:id", get(vs_baseline))
+        .route("/keyboard/:id", get(keyboard_page))
+        .route("/lineage/:id", get(lineage))
+        .route("/ws/worker", get(worker_socket))
+        .route(HTMX_BUNDLED, get(htmx_js))
+        .route(APP_JS, get(app_js))
+        .merge(admin)
+        .layer(middleware::from_fn_with_state(state.clone(), security_headers))
+        .with_state(state.clone());
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 8000));
+    let shutdown = state.lock().await.shutdown.clone();
     axum::Server::bind(&addr)
         .serve(router.into_make_service())
+        .with_graceful_shutdown(async move { shutdown.notified().await })
         .await
-        .unwrap()
+        .unwrap();
+
+    // give stopped workers and the last saves a moment to wrap up
+    let tasks: Vec<JoinHandle<()>> = state.lock().await.tasks.drain(..).collect();
+    let finished = tokio::time::timeout(SHUTDOWN_GRACE, async {
+        for task in tasks {
+            task.await.ok();
+        }
+    });
+    if finished.await.is_err() {
+        warn!("exiting with tasks still running");
+    }
+    info!("shut down");
 }
 
-async fn root(State(_shared_state): State<SharedState>) -> Html<String> {
-    let head = hyprtxt!(
+fn head(config: &Config) -> String {
+    // the bundled copy and the pinned cdn build are the same file, checked
+    // against its hash. anything else is trusted as configured
+    let pinned = config.htmx_src == HTMX_BUNDLED || config.htmx_src == HTMX_CDN;
+    let integrity = if pinned { HTMX_CDN_INTEGRITY } else { "" };
+    hyprtxt!(
         "head" {
             "meta"* { "charset"="UTF-8" }
             "meta"* { 
@@ -122,19 +1390,49 @@ async fn root(State(_shared_state): State<SharedState>) -> Html<String> {
                 "name"="viewport"
                 "content"="width=device-width, initial-scale=1.0"
             }
-            "title" { $: "keyboard generation nonsense" }
+            "title" { $: PAGE_TITLE }
+            "style" {
+                $: format!(
+                    ".keyboard .button {{ width: {}em; height: {}em; }} \
+                     .keyboard .home {{ border: 2px solid #2a7; }} .keyboard .locked {{ background: #fd8; }} \
+                     .keyboard .sfb {{ outline: 2px dashed #c33; }}",
+                    KEY_EM,
+                    KEY_EM,
+                )
+            }
             "script" {
-                "src"="https://unpkg.com/htmx.org@1.9.2"
-                "integrity"="sha384-L6OqL9pRWyyFU3+/bjdSri+iIphTN/bvYyM37tICVyOJkWZLpP2vGn6VUEXgzg6h"
+                "src"=escape_html(&config.htmx_src)
+                "integrity"=integrity
                 "crossorigin"="anonymous"
             }
+            "script" { "src"=APP_JS }
         }
-    );
-    let body = hyprtxt!(
-        "body" {
-            "h1" { $: "Keyboard Generation" }
-            "h3" { $: "Workers" }
-            
+    )
+}
+
+async fn app_js() -> impl IntoResponse {
+    const APP: &str = include_str!("../assets/app.js");
+    ([(CONTENT_TYPE, "text/javascript; charset=utf-8")], APP)
+}
+
+/// htmx 1.9.3, see assets/htmx-LICENSE
+async fn htmx_js() -> impl IntoResponse {
+    const HTMX: &str = include_str!("../assets/htmx.min.js");
+    ([(CONTENT_TYPE, "text/javascript; charset=utf-8")], HTMX)
+}
+
+async fn root(State(shared_state): State<SharedState>) -> Html<String> {
+    let (head, pinned, stalled, status) = {
+        let state = shared_state.lock().await;
+        let status = status_poller(&state, hyprtxt!("h3" { $: "Status: INIT" }));
+        (head(&state.config), pinned_input(&state.locks), stall_warning(&state), status)
+    };
+    let body = hyprtxt!(
+        "body" {
+            "h1" { $: "Keyboard Generation" }
+            $: stalled
+            "h3" { $: "Workers" }
+            
             "form" {
                 "hx-post"="/add-server"
                 "hx-target"="#status"
@@ -148,6 +1446,22 @@ async fn root(State(_shared_state): State<SharedState>) -> Html<String> {
                         "type"="text"
                         "name"="host"
                         "id"="host"
+                        "hx-get"="/validate-host"
+                        "hx-trigger"="blur"
+                        "hx-target"="#host-validation"
+                    }
+                    "span" { "id"="host-validation" }
+                }
+                "div" {
+                    "label" {
+                        "for"="host_group"
+                        $: "Group"
+                    }
+                    "input" {
+                        "type"="text"
+                        "name"="group"
+                        "id"="host_group"
+                        "placeholder"="none"
                     }
                 }
                 "br"* {}
@@ -157,6 +1471,45 @@ async fn root(State(_shared_state): State<SharedState>) -> Html<String> {
                 }
             }
             
+            "h3" { $: "Import Layout" }
+
+            "form" {
+                "hx-post"="/import-layout"
+                "hx-target"="#import-result"
+
+                "div" {
+                    "label" {
+                        "for"="layout"
+                        $: "Layout"
+                    }
+                    "input" {
+                        "type"="text"
+                        "name"="layout"
+                        "id"="layout"
+                        "size"="56"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="score"
+                        $: "Score"
+                    }
+                    "input" {
+                        "type"="number"
+                        "step"="any"
+                        "name"="score"
+                        "id"="score"
+                        "placeholder"="coordinator effort"
+                    }
+                }
+                "br"* {}
+                "button" {
+                    "type"="submit"
+                    $: "Import"
+                }
+                "div" { "id"="import-result" }
+            }
+
             "h3" { $: "Start Job" }
             
             "form" {
@@ -183,6 +1536,10 @@ async fn root(State(_shared_state): State<SharedState>) -> Html<String> {
                         "type"="number"
                         "name"="batch_size"
                         "id"="batch_size"
+                        "hx-get"="/validate-job"
+                        "hx-trigger"="change"
+                        "hx-include"="#batch_size, #batches, #job_group, #batch_schedule"
+                        "hx-target"="#job-validation"
                     }
                 }
                 "div" {
@@ -194,8 +1551,152 @@ async fn root(State(_shared_state): State<SharedState>) -> Html<String> {
                         "type"="number"
                         "name"="batches"
                         "id"="batches"
+                        "hx-get"="/validate-job"
+                        "hx-trigger"="change"
+                        "hx-include"="#batch_size, #batches, #job_group, #batch_schedule"
+                        "hx-target"="#job-validation"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="batch_schedule"
+                        $: "Batch Sizes"
+                    }
+                    "input" {
+                        "type"="text"
+                        "name"="batch_schedule"
+                        "id"="batch_schedule"
+                        "placeholder"="all the same, 8,16,32 or x1.5"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="pinned"
+                        $: "Pinned Keys"
+                    }
+                    $: pinned
+                }
+                "div" {
+                    "label" {
+                        "for"="mutable_positions"
+                        $: "Positions That Move"
+                    }
+                    "input" {
+                        "type"="text"
+                        "name"="mutable_positions"
+                        "id"="mutable_positions"
+                        "placeholder"="all"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="tags"
+                        $: "Tags"
+                    }
+                    "input" {
+                        "type"="text"
+                        "name"="tags"
+                        "id"="tags"
+                        "placeholder"="english, seeded"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="board"
+                        $: "Board"
+                    }
+                    "input" {
+                        "type"="text"
+                        "name"="board"
+                        "id"="board"
+                        "list"="boards"
+                        "placeholder"="generic"
+                    }
+                    "datalist" {
+                        "id"="boards"
+                        $: boards::BOARDS
+                            .iter()
+                            .map(|b| hyprtxt!("option" { "value"=b.name }))
+                            .collect::<String>()
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="min_completion_fraction"
+                        $: "Finish At"
+                    }
+                    "input" {
+                        "type"="number"
+                        "step"="any"
+                        "min"="0"
+                        "max"="1"
+                        "name"="min_completion_fraction"
+                        "id"="min_completion_fraction"
+                        "placeholder"="1.0"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="job_group"
+                        $: "Worker Group"
+                    }
+                    "input" {
+                        "type"="text"
+                        "name"="group"
+                        "id"="job_group"
+                        "placeholder"="all workers"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="max_runtime_secs"
+                        $: "Time Limit (s)"
+                    }
+                    "input" {
+                        "type"="number"
+                        "min"="1"
+                        "name"="max_runtime_secs"
+                        "id"="max_runtime_secs"
+                        "placeholder"="none"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="use_seed"
+                        $: "Start From Seed Keyboard"
+                    }
+                    "input" {
+                        "type"="checkbox"
+                        "name"="use_seed"
+                        "id"="use_seed"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="seed"
+                        $: "Seed"
+                    }
+                    "input" {
+                        "type"="number"
+                        "min"="0"
+                        "name"="seed"
+                        "id"="seed"
+                        "placeholder"="random"
+                    }
+                }
+                "div" {
+                    "label" {
+                        "for"="completion_webhook"
+                        $: "Completion Webhook"
+                    }
+                    "input" {
+                        "type"="url"
+                        "name"="completion_webhook"
+                        "id"="completion_webhook"
+                        "placeholder"="none"
                     }
                 }
+                "div" { "id"="job-validation" }
                 "br"* {}
                 "button" {
                     "type"="submit"
@@ -203,11 +1704,106 @@ async fn root(State(_shared_state): State<SharedState>) -> Html<String> {
                 }
             }
 
+            "form" {
+                "hx-post"="/set-batches"
+                "hx-target"="#set-batches-result"
+
+                "div" {
+                    "label" {
+                        "for"="new_batches"
+                        $: "Change Batches"
+                    }
+                    "input" {
+                        "type"="number"
+                        "name"="batches"
+                        "id"="new_batches"
+                        "min"="1"
+                    }
+                    "button" {
+                        "type"="submit"
+                        $: "Set"
+                    }
+                }
+                "div" { "id"="set-batches-result" }
+            }
+
             "div" {
-                "hx-get"="/update"
-                "hx-trigger"="every 5s"
-                "id"="status"
-                "h3" { $: "Status: INIT" }
+                "label" {
+                    "for"="min_score"
+                    $: "Score Threshold"
+                }
+                "input" {
+                    "type"="number"
+                    "step"="any"
+                    "name"="min_score"
+                    "id"="min_score"
+                }
+                "label" {
+                    "for"="labels"
+                    $: " Show Positions"
+                }
+                "input" {
+                    "type"="checkbox"
+                    "name"="labels"
+                    "id"="labels"
+                    "value"="index"
+                }
+                "label" {
+                    "for"="sort"
+                    $: " Rank By Metric"
+                }
+                "input" {
+                    "type"="text"
+                    "name"="sort"
+                    "id"="sort"
+                    "placeholder"="score"
+                }
+                "label" {
+                    "for"="top"
+                    $: " Rows"
+                }
+                "input" {
+                    "type"="number"
+                    "min"="1"
+                    "name"="top"
+                    "id"="top"
+                    "placeholder"=LEADERBOARD_SIZE
+                }
+            }
+            "button" {
+                "hx-post"="/stop-all"
+                "hx-target"="#status"
+                $: "Stop All"
+            }
+            "button" {
+                "hx-post"="/restart-job"
+                "hx-target"="#status"
+                $: "Run Again"
+            }
+            "button" {
+                "hx-post"="/reset"
+                "hx-target"="#status"
+                $: "Reset"
+            }
+            $: status
+            "a" {
+                "href"="/jobs"
+                $: "Jobs"
+            }
+            $: " "
+            "a" {
+                "href"="/history"
+                $: "History"
+            }
+            $: " "
+            "a" {
+                "href"="/convergence"
+                $: "Convergence"
+            }
+            $: " "
+            "a" {
+                "href"="/position-heatmap"
+                $: "Heatmap"
             }
             "br"* {}
             "br"* {}
@@ -218,217 +1814,4343 @@ async fn root(State(_shared_state): State<SharedState>) -> Html<String> {
             }
         }
     );
-    Html(vec!["<!DOCTYPE html>".to_string(), head, body].join(""))
+    Html(["<!DOCTYPE html>".to_string(), head, body].join(""))
 }
 
-async fn update(State(shared_state): State<SharedState>) -> Html<String> {
+/// redrawn from scratch on every request, so it's always current
+async fn update(
+    State(shared_state): State<SharedState>,
+    Query(req): Query<UpdateReq>,
+) -> Response {
     let state = shared_state.lock().await;
+    let body = match req.job {
+        Some(id) if id != state.job_id => match archived_status(&state, id) {
+            Some(body) => body,
+            None => return (StatusCode::NOT_FOUND, "no job with that id").into_response(),
+        },
+        _ => status_poller(&state, status(&state, &req)),
+    };
+    ([(CACHE_CONTROL, "no-store")], Html(body)).into_response()
+}
 
-    let update_layout = |inner: String| -> Html<String> {
-        Html(hyprtxt!(
-            "div" {
-                $: inner
+/// the element that keeps `/update` coming, swapped out whole on each one so
+/// it polls less often while there's no job running
+fn status_poller(state: &AppState, inner: String) -> String {
+    let every = if state.is_running() { ACTIVE_POLL } else { IDLE_POLL };
+    hyprtxt!(
+        "div" {
+            "hx-get"="/update"
+            "hx-trigger"=format!("{}, {} from:body", every, JOB_STARTED_EVENT)
+            "hx-include"="#min_score, #labels, #sort, #top"
+            "hx-swap"="outerHTML"
+            "id"="status"
+            $: inner
+        }
+    )
+}
+
+/// what's left of a finished job once another has started, from its history
+/// record
+fn archived_status(state: &AppState, job_id: u64) -> Option<String> {
+    let job = state.history.iter().find(|job| job.job_id == job_id)?;
+    Some(hyprtxt!(
+        "div" {
+            "h3" {
+                $: "Job "
+                $: escape_html(&job.job_name)
+                $: ", "
+                $: job.outcome.label()
             }
-        ))
-    };
-    
-    if state.running {
-        update_layout(hyprtxt!(
+            "p" {
+                $: job.completed
+                $: "/"
+                $: job.batches
+                $: " batches of "
+                $: job.batch_size
+            }
+            $: match &job.best {
+                Some(best) => hyprtxt!("p" { $: "Best score " $: best.score }) + &keyboard(best, &[], "", Labels::Glyph),
+                None => hyprtxt!("p" { $: "No keyboards" }),
+            }
+        }
+    ))
+}
+
+/// everything under `#status`, also sent back by endpoints that change it so
+/// the page doesn't wait for the next poll
+fn status(state: &AppState, UpdateReq { min_score, labels, sort, top, .. }: &UpdateReq) -> String {
+    let min_score = min_score.as_deref().and_then(|s| s.trim().parse::<f32>().ok());
+    let (labels, sort) = (*labels, sort.trim());
+
+    let inner = if state.is_running() {
+        hyprtxt!(
             "div" {
                 "h3" {
                     $: "Job "
-                    $: state.job_name
+                    $: escape_html(&state.job_name)
                     $: " Running"
                 }
-                $: keyboard(state.keyboards.clone())
+                $: key_set_warning(state)
+                $: progress(state)
+                $: waiting_for_workers(state)
+                $: inflight_table(state)
+                $: results(state, min_score, labels, sort, *top)
             }
-        ))
-    } else if state.keyboards.len() > 0 {
-        update_layout(hyprtxt!(
+        )
+    } else if !state.keyboards.is_empty() {
+        hyprtxt!(
             "div" {
                 "h3" {
                     $: "Job "
-                    $: state.job_name
+                    $: escape_html(&state.job_name)
                     $: " Complete"
                 }
-                $: keyboard(state.keyboards.clone())
+                $: key_set_warning(state)
+                $: progress(state)
+                $: results(state, min_score, labels, sort, *top)
             }
-        ))
+        )
     } else {
-        update_layout(hyprtxt!(
+        hyprtxt!(
             "h3" { $: "INIT" }
-        ))
-    }
+        ) + &invalid_counts(state)
+    };
+    hyprtxt!(
+        "div" {
+            $: page_title(state)
+            $: inner
+            $: favorites(state)
+            $: worker_list(state)
+        }
+    )
 }
 
-async fn add_server(
-    State(shared_state): State<SharedState>,
-    Form(add_server_req): Form<AddServerReq>,
-) -> Html<String> {
-    let mut state = shared_state.lock().await;
-    state.hosts.push(add_server_req.host);
+/// htmx sets the document's title from a swapped in `<title>`, so a tab in
+/// the background still shows how the job is going
+fn page_title(state: &AppState) -> String {
+    let title = if state.job_id == 0 {
+        PAGE_TITLE.to_string()
+    } else {
+        let done = state.completed as f32 / state.batches.max(1) as f32;
+        match state.best() {
+            Some(best) => format!("[{:.0}%] {:.2} – {}", done * 100.0, best.score, escape_html(&state.job_name)),
+            None => format!("[{:.0}%] {}", done * 100.0, escape_html(&state.job_name)),
+        }
+    };
+    hyprtxt!("title" { $: title })
+}
 
-    Html(hyprtxt!(
+/// a page that follows the current job without any of the controls, for
+/// sharing
+async fn view(State(shared_state): State<SharedState>) -> Html<String> {
+    let head = head(&shared_state.lock().await.config);
+    let body = hyprtxt!(
+        "body" {
+            "h1" { $: "Keyboard Generation" }
+            "div" {
+                "hx-get"="/view/status"
+                "hx-trigger"="load, every 5s"
+                "id"="status"
+            }
+        }
+    );
+    Html(["<!DOCTYPE html>".to_string(), head, body].join(""))
+}
+
+/// progress, the best keyboard and the scores, nothing that changes anything
+async fn view_status(State(shared_state): State<SharedState>) -> impl IntoResponse {
+    let state = shared_state.lock().await;
+    let heading = if state.is_running() {
+        "Running"
+    } else if !state.keyboards.is_empty() {
+        "Complete"
+    } else {
+        return ([(CACHE_CONTROL, "no-store")], Html(hyprtxt!("h3" { $: "No job yet" })))
+    };
+    let ranked: Vec<&Keyboard> = state.leaders.iter().collect();
+    let body = hyprtxt!(
+        "div" {
+            $: page_title(&state)
+            "h3" {
+                $: "Job "
+                $: escape_html(&state.job_name)
+                $: " "
+                $: heading
+            }
+            $: progress(&state)
+            $: ranked.first().map_or(String::new(), |k| keyboard(k, &state.pinned, &state.board, Labels::Glyph))
+            $: scoreboard(&ranked)
+        }
+    );
+    ([(CACHE_CONTROL, "no-store")], Html(body))
+}
+
+fn worker_list(state: &AppState) -> String {
+    if state.hosts.is_empty() {
+        return String::new()
+    }
+    hyprtxt!(
         "div" {
             "h3" { $: "Current Servers" }
             "ul" {
                 $: state.hosts
                     .iter()
-                    .map(|s| hyprtxt!("li" { $: s }))
+                    .take(state.config.max_rendered)
+                    .map(|s| hyprtxt!("li" {
+                        $: escape_html(s)
+                        $: if state.sockets.contains_key(s) { " (websocket)" } else { "" }
+                        $: state.groups.get(s).map_or(String::new(), |g| format!(" [{}]", escape_html(g)))
+                        $: match state.host_scores.get(s) {
+                            Some(&(sum, count)) => format!(
+                                ", average score {:.3} over {} keyboards",
+                                sum / count as f64,
+                                count,
+                            ),
+                            None => String::new(),
+                        }
+                        $: if can_restart(state, s) { restart_button(s) } else { String::new() }
+                        $: remove_button(s)
+                    }))
                     .collect::<Vec<String>>()
                     .concat()
             }
+            $: truncation_note(state.hosts.len().min(state.config.max_rendered), state.hosts.len(), "/debug/state")
         }
-    ))
+    )
 }
 
-#[axum::debug_handler]
-async fn start_job(
+async fn add_server(
     State(shared_state): State<SharedState>,
-    Form(StartJobReq { job_name, batch_size, batches }): Form<StartJobReq>,
+    Form(add_server_req): Form<AddServerReq>,
 ) -> Html<String> {
     let mut state = shared_state.lock().await;
-    if state.running {
-        return Html(hyprtxt!("h1" {
+    let host = normalize_host(&add_server_req.host);
+    if host == LOCAL_HOST && !cfg!(feature = "local-worker") {
+        return Html(hyprtxt!("h3" {
             "style"="color: red;"
-            $: "ERROR: job already in progress"
+            $: "ERROR: built without the local-worker feature"
         }))
     }
-    state.job_name = job_name;
-    state.batches = batches;
-    state.batch_size = batch_size;
-    state.running = true;
-    let hosts = state.hosts.clone();
-
-    for host in hosts {
-        let thread_state = shared_state.clone();
-        tokio::spawn(async move {
-            let client = reqwest::Client::new();
-            loop {
-                let resp = client
-                    .get(host.to_string() + "/update")
-                    .send()
-                    .await
-                    .expect("failed request")
-                    .json()
-                    .await
-                    .expect("failed parse");
-                
-                match resp {
-                    UpdateResp::InProgress { .. } => thread::sleep(Duration::new(5, 0)),
-                    UpdateResp::Init => {
-                        let mut state = thread_state.lock().await;
-                        if state.completed < state.batches {
-                            client
-                                .post(host.to_string() + "/new")
-                                .json(&BatchReq {
-                                    job_name: state.job_name.clone(),
-                                    device_name: host.to_string(),
-                                    batch_size: state.batch_size,
-                                    batch_number: state.completed,
-                                })
-                                .send()
-                                .await
-                                .expect("failed post");
-                        } else {
-                            state.running = false;
-                            break;
-                        }
-                    },
-                    UpdateResp::BatchComplete { keyboards } => {
-                        let mut state = thread_state.lock().await;
-                        state.completed += 1;
-                        state.keyboards.extend(keyboards.into_iter());
-                        if state.completed < state.batches {
-                            client
-                                .post(host.to_string() + "/new")
-                                .json(&BatchReq {
-                                    job_name: state.job_name.clone(),
-                                    device_name: host.to_string(),
-                                    batch_size: state.batch_size,
-                                    batch_number: state.completed,
-                                })
-                                .send()
-                                .await
-                                .unwrap();
-                        } else {
-                            state.running = false;
-                            break;
-                        }
-                    },
-                }
-            }
-        });
+    // the same worker twice would be polled by two tasks at once
+    if state.hosts.contains(&host) {
+        return Html(job_error("that worker is already added").0 + &status(&state, &UpdateReq::default()))
     }
+    let group = add_server_req.group.trim();
+    if !group.is_empty() {
+        state.groups.insert(host.clone(), group.to_string());
+    }
+    state.hosts.push(host);
+    save_hosts(&state).await;
 
-    Html(hyprtxt!(
-        "div" {
-            "h3" {
-                $: "Job "
-                $: state.job_name
-                $: " Started"
-            }
-            "button" {
-                $: "update"
-            }
-            "button" {
-                $: "stop job"
-            }
-        }
-    ))
+    Html(status(&state, &UpdateReq::default()))
 }
 
-fn keyboard(mut keyboards: Vec<Keyboard>) -> String {
-    if keyboards.len() == 0 { return "".to_string()}
-    keyboards.sort_by(|a, b| a.score.partial_cmp(&b.score).unwrap());
-    let keyboard = &keyboards[0];
-    hyprtxt!(
-        "div" {
-            "class"="keyboard"
-            "div" {
-                "class"="row"
-                $: &keyboard.keys[0..13]
-                    .iter()
-                    .map(|key| hyprtxt!("button" {
-                        "class"="button"
-                        $: key.upper
+/// takes a worker out of the pool. a job it's part of stops giving it batches
+/// and hands the ones it's on to another worker. a host that isn't in the pool
+/// leaves the list as it was, and a running job's last worker stays so the
+/// job can finish.
+async fn remove_server(
+    State(shared_state): State<SharedState>,
+    Form(RemoveServerReq { host }): Form<RemoveServerReq>,
+) -> Html<String> {
+    let host = normalize_host(&host);
+    let mut state = shared_state.lock().await;
+    let others = state.claims.keys().any(|h| *h != host && state.hosts.contains(h));
+    if state.is_running() && state.claims.contains_key(&host) && !others {
+        return Html(job_error("can't remove the last worker while a job is running").0 + &status(&state, &UpdateReq::default()))
+    }
+    if state.hosts.contains(&host) {
+        state.hosts.retain(|h| *h != host);
+        state.groups.remove(&host);
+        state.inflight_limits.remove(&host);
+        save_hosts(&state).await;
+        info!(host, "worker removed");
+        if state.is_running() && state.claims.remove(&host).is_some() {
+            log_event(&mut state, EventKind::Removed { host });
+        }
+    }
+    Html(status(&state, &UpdateReq::default()))
+}
+
+/// the workers added by hand, what's kept in `hosts_file`
+#[derive(Serialize, Deserialize, Default)]
+struct WorkerPool {
+    hosts: Vec<String>,
+    #[serde(default)]
+    groups: HashMap<String, String>,
+    #[serde(default)]
+    inflight_limits: HashMap<String, usize>,
+}
+
+/// writes the worker pool to `hosts_file`, leaving out websocket workers since
+/// they register themselves when they connect. called with the state locked so
+/// two saves can't land out of order.
+async fn save_hosts(state: &AppState) {
+    let hosts: Vec<String> = state.hosts.iter().filter(|h| !state.sockets.contains_key(*h)).cloned().collect();
+    let pool = WorkerPool {
+        groups: state.groups.iter().filter(|(h, _)| hosts.contains(h)).map(|(h, g)| (h.clone(), g.clone())).collect(),
+        inflight_limits: state.inflight_limits.iter().filter(|(h, _)| hosts.contains(h)).map(|(h, &l)| (h.clone(), l)).collect(),
+        hosts,
+    };
+    let path = &state.config.hosts_file;
+    let json = serde_json::to_vec_pretty(&pool).expect("serializable worker pool");
+    let saved = match path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        Some(dir) => tokio::fs::create_dir_all(dir).await,
+        None => Ok(()),
+    };
+    if let Err(e) = saved.and(tokio::fs::write(path, json).await) {
+        warn!(path = %path.display(), error = %e, "couldn't save worker pool");
+    }
+}
+
+/// the worker pool saved by the last run, empty if there isn't one
+async fn read_hosts(path: &std::path::Path) -> WorkerPool {
+    let json = match tokio::fs::read(path).await {
+        Ok(json) => json,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return WorkerPool::default(),
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "couldn't read worker pool");
+            return WorkerPool::default()
+        },
+    };
+    let mut pool: WorkerPool = match serde_json::from_slice(&json) {
+        Ok(pool) => pool,
+        Err(e) => {
+            warn!(path = %path.display(), error = %e, "worker pool file isn't valid, starting without it");
+            return WorkerPool::default()
+        },
+    };
+    pool.hosts.retain(|host| host != LOCAL_HOST || cfg!(feature = "local-worker"));
+    let mut seen = HashSet::new();
+    pool.hosts.retain(|host| seen.insert(host.clone()));
+    info!(path = %path.display(), hosts = pool.hosts.len(), "loaded worker pool");
+    pool
+}
+
+/// lets a worker connect to us instead of being polled, registering it as a
+/// host if it isn't one already
+async fn worker_socket(
+    ws: WebSocketUpgrade,
+    State(shared_state): State<SharedState>,
+    Query(WorkerSocketReq { host }): Query<WorkerSocketReq>,
+) -> Response {
+    let host = normalize_host(&host);
+    ws.on_upgrade(move |socket| serve_worker_socket(socket, host, shared_state))
+}
+
+async fn serve_worker_socket(socket: WebSocket, host: String, shared_state: SharedState) {
+    let (tx, mut outgoing) = mpsc::unbounded_channel::<ControlMsg>();
+    let (updates_tx, updates) = mpsc::unbounded_channel();
+    {
+        let mut state = shared_state.lock().await;
+        if !state.hosts.contains(&host) {
+            state.hosts.push(host.clone());
+        }
+        let worker = WorkerSocket { tx: tx.clone(), updates: Arc::new(Mutex::new(updates)) };
+        state.sockets.insert(host.clone(), worker);
+    }
+
+    let (mut sink, mut stream) = socket.split();
+    let send = async {
+        while let Some(msg) = outgoing.recv().await {
+            let text = serde_json::to_string(&msg).expect("control message serializes");
+            if sink.send(Message::Text(text)).await.is_err() {
+                break;
+            }
+        }
+    };
+    let recv = async {
+        while let Some(Ok(frame)) = stream.next().await {
+            let update = match frame {
+                Message::Text(text) => serde_json::from_str(&text).map_err(|e| e.to_string()),
+                Message::Binary(bytes) => rmp_serde::from_slice(&bytes).map_err(|e| e.to_string()),
+                Message::Close(_) => break,
+                _ => continue,
+            };
+            match update {
+                Ok(update) => if updates_tx.send(update).is_err() { break },
+                Err(_) => continue,
+            }
+        }
+    };
+    tokio::select! {
+        _ = send => {},
+        _ = recv => {},
+    }
+
+    // only forget the socket if the worker hasn't already reconnected
+    let mut state = shared_state.lock().await;
+    if state.sockets.get(&host).is_some_and(|w| w.tx.same_channel(&tx)) {
+        state.sockets.remove(&host);
+    }
+}
+
+#[axum::debug_handler]
+async fn start_job(
+    State(shared_state): State<SharedState>,
+    Form(req): Form<StartJobReq>,
+) -> Response {
+    let seed_layout = if req.use_seed == "on" {
+        let config = &shared_state.lock().await.config;
+        let (seed_file, alphabet) = (config.seed_file.clone(), config.alphabet.clone());
+        match read_seed(&seed_file, &alphabet).await {
+            Ok(keyboard) => Some(keyboard.keys.to_vec()),
+            Err(e) => return job_error(&e).into_response(),
+        }
+    } else {
+        None
+    };
+    let mut state = shared_state.lock().await;
+    if state.hosts.is_empty() {
+        return job_error("add a worker before starting a job").into_response()
+    }
+    let group = req.group.trim();
+    let hosts = job_hosts(&state, group);
+    if hosts.is_empty() {
+        return job_error(&format!("no workers in group \"{}\"", group)).into_response()
+    }
+    let pinned = match parse_pinned(&req.pinned) {
+        Ok(pinned) => pinned,
+        Err(e) => return job_error(&e).into_response(),
+    };
+    let min_completion = match parse_fraction(&req.min_completion_fraction) {
+        Ok(min_completion) => min_completion,
+        Err(e) => return job_error(&e).into_response(),
+    };
+    let max_runtime = match parse_runtime(&req.max_runtime_secs) {
+        Ok(max_runtime) => max_runtime,
+        Err(e) => return job_error(&e).into_response(),
+    };
+    let seed = match parse_seed(&req.seed) {
+        Ok(seed) => seed,
+        Err(e) => return job_error(&e).into_response(),
+    };
+    let mutable = match parse_positions(&req.mutable_positions) {
+        Ok(mutable) => mutable,
+        Err(e) => return job_error(&e).into_response(),
+    };
+    let schedule = match parse_schedule(&req.batch_schedule) {
+        Ok(schedule) => schedule,
+        Err(e) => return job_error(&e).into_response(),
+    };
+    if let Err(e) = validate_job(req.batch_size, req.batches, &schedule) {
+        return job_error(&e).into_response()
+    }
+    let webhook = match req.completion_webhook.trim() {
+        "" => state.config.completion_webhook.clone(),
+        url => match parse_webhook(url) {
+            Ok(url) => Some(url),
+            Err(e) => return job_error(&e).into_response(),
+        },
+    };
+    if !state.try_start() {
+        return (StatusCode::CONFLICT, job_error("job already in progress")).into_response()
+    }
+    state.job_name = req.job_name;
+    state.batches = req.batches;
+    state.batch_size = req.batch_size;
+    state.schedule = schedule;
+    state.min_completion = min_completion;
+    state.max_runtime = max_runtime;
+    state.webhook = webhook;
+    state.seed = seed;
+    state.seed_layout = seed_layout;
+    state.mutable = mutable;
+    state.pinned = pinned;
+    state.tags = parse_tags(&req.tags);
+    state.board = req.board.trim().to_string();
+    launch_job(&mut state, &shared_state, hosts);
+    ([("HX-Trigger", JOB_STARTED_EVENT)], job_started(&state)).into_response()
+}
+
+/// runs the last job again from scratch on the same workers, the previous
+/// run is already in the history
+async fn restart_job(State(shared_state): State<SharedState>) -> Response {
+    let mut state = shared_state.lock().await;
+    // a reset throws the last job away along with its results
+    if state.job_id == 0 || state.batches == 0 {
+        return job_error("no job to restart").into_response()
+    }
+    let hosts: Vec<String> = state.hosts
+        .iter()
+        .filter(|host| state.claims.contains_key(*host))
+        .cloned()
+        .collect();
+    if hosts.is_empty() {
+        return job_error("none of the job's workers are left").into_response()
+    }
+    if !state.try_start() {
+        return (StatusCode::CONFLICT, job_error("job already in progress")).into_response()
+    }
+    launch_job(&mut state, &shared_state, hosts);
+    ([("HX-Trigger", JOB_STARTED_EVENT)], job_started(&state)).into_response()
+}
+
+/// clears the last job's results and sets `hosts` working on the one the
+/// state now describes, which has already won `try_start`
+fn launch_job(state: &mut AppState, shared_state: &SharedState, hosts: Vec<String>) {
+    state.deadline = state.max_runtime.map(|limit| Instant::now() + limit);
+    state.last_result = Some(Instant::now());
+    state.started = Some(Instant::now());
+    state.keyboards.clear();
+    state.leaders.clear();
+    state.convergence.clear();
+    state.previous_best = None;
+    state.glyph_sets.clear();
+    state.host_scores.clear();
+    state.host_stats.clear();
+    state.dispatched_at.clear();
+    state.completed = 0;
+    state.acked.clear();
+    state.batch_results.clear();
+    state.empty_batches = 0;
+    state.requeued.clear();
+    state.retried.clear();
+    state.restarts.clear();
+    state.dispatched = 0;
+    state.job_id += 1;
+    state.tasks.clear();
+    state.claims = hosts.iter().map(|host| (host.clone(), 0)).collect();
+    state.inflight = hosts.iter().map(|host| (host.clone(), 0)).collect();
+    state.batch_progress.clear();
+    let job_id = state.job_id;
+    state.events.retain(|&id, _| id + EVENT_LOG_JOBS > job_id);
+    log_event(state, EventKind::Start { hosts: hosts.clone() });
+    let job = JobHandle {
+        id: state.job_id,
+        encoding: state.config.encoding,
+        poll_interval: state.config.poll_interval,
+        probe_interval: state.config.probe_interval,
+        parse_retry: state.config.parse_retry,
+        init_timeout: state.config.init_timeout,
+        min_dispatch_interval: state.config.min_dispatch_interval,
+        client: state.client.clone(),
+        results: state.results.clone(),
+        stop: state.stop.clone(),
+    };
+
+    for host in hosts {
+        let thread_state = shared_state.clone();
+        #[cfg(feature = "local-worker")]
+        if host == LOCAL_HOST {
+            state.tasks.push(tokio::spawn(run_local(thread_state, job.clone())));
+            continue;
+        }
+        state.tasks.push(tokio::spawn(run_host(host, thread_state, job.clone())));
+    }
+    if let Some(deadline) = state.deadline {
+        state.tasks.push(tokio::spawn(enforce_deadline(deadline, shared_state.clone(), job.clone())));
+    }
+}
+
+fn job_started(state: &AppState) -> Html<String> {
+    let mut hosts: Vec<String> = state.claims.keys().cloned().collect();
+    hosts.sort();
+    let keyboards = state.schedule.total(state.batch_size, state.batches);
+    Html(hyprtxt!(
+        "div" {
+            "h3" {
+                $: "Job "
+                $: escape_html(&state.job_name)
+                $: " Started"
+            }
+            $: work_estimate(state, &hosts, keyboards)
+            "button" {
+                $: "update"
+            }
+            "button" {
+                "hx-post"="/stop-job"
+                "hx-target"="#status"
+                "hx-swap"="innerHTML"
+                $: "stop job"
+            }
+        }
+    ))
+}
+
+/// throws away the last job's results, favorites stay
+async fn reset(State(shared_state): State<SharedState>) -> Response {
+    let mut state = shared_state.lock().await;
+    if state.is_running() {
+        return (StatusCode::CONFLICT, job_error("stop the job before resetting")).into_response()
+    }
+    state.keyboards.clear();
+    state.leaders.clear();
+    state.convergence.clear();
+    state.previous_best = None;
+    state.invalid.clear();
+    state.glyph_sets.clear();
+    state.host_scores.clear();
+    state.host_stats.clear();
+    state.dispatched_at.clear();
+    state.completed = 0;
+    state.acked.clear();
+    state.batch_results.clear();
+    state.empty_batches = 0;
+    state.requeued.clear();
+    state.retried.clear();
+    state.restarts.clear();
+    state.dispatched = 0;
+    state.batches = 0;
+    state.claims.clear();
+    state.job_name.clear();
+    Html(hyprtxt!("h3" { $: "INIT" }) + &favorites(&state)).into_response()
+}
+
+/// stops the running job. its worker tasks wake up and leave their loops, and
+/// the batches still out are abandoned.
+async fn stop_job(State(shared_state): State<SharedState>) -> Html<String> {
+    let mut state = shared_state.lock().await;
+    if !state.is_running() {
+        return Html(hyprtxt!("h3" { $: "No job running" }) + &status(&state, &UpdateReq::default()))
+    }
+    finish_job(&mut state, JobOutcome::Stopped);
+    Html(hyprtxt!(
+        "h3" {
+            $: "Job "
+            $: escape_html(&state.job_name)
+            $: " Stopped"
+        }
+    ) + &status(&state, &UpdateReq::default()))
+}
+
+/// stops whatever is running so every worker is free. there's only ever one
+/// job at a time for now, so that's at most one.
+async fn stop_all(State(shared_state): State<SharedState>) -> Html<String> {
+    let mut state = shared_state.lock().await;
+    let stopped = if state.is_running() {
+        finish_job(&mut state, JobOutcome::Stopped);
+        1
+    } else {
+        0
+    };
+    Html(hyprtxt!(
+        "h3" {
+            $: "Stopped "
+            $: stopped
+            $: if stopped == 1 { " job" } else { " jobs" }
+        }
+    ) + &status(&state, &UpdateReq::default()))
+}
+
+/// stops the job at `deadline` if it's still going by then
+async fn enforce_deadline(deadline: Instant, shared_state: SharedState, job: JobHandle) {
+    loop {
+        job.pause(deadline.saturating_duration_since(Instant::now())).await;
+        let mut state = shared_state.lock().await;
+        if !state.is_current(job.id) {
+            break;
+        }
+        if Instant::now() >= deadline {
+            warn!(job = %state.job_name, "job hit its time limit");
+            finish_job(&mut state, JobOutcome::TimeLimit);
+            break;
+        }
+    }
+}
+
+/// extends or cuts short the running job. batches already handed out still
+/// count when they come back, even if that's more than the new total.
+async fn set_batches(
+    State(shared_state): State<SharedState>,
+    Form(SetBatchesReq { batches }): Form<SetBatchesReq>,
+) -> Html<String> {
+    let mut state = shared_state.lock().await;
+    if !state.is_running() {
+        return job_error("no job running")
+    }
+    if batches < state.completed {
+        return job_error(&format!("{} batches are already complete", state.completed))
+    }
+    if let Err(e) = validate_job(state.batch_size, batches, &state.schedule) {
+        return job_error(&e)
+    }
+    state.batches = batches;
+    if state.completed >= state.required() {
+        finish_job(&mut state, JobOutcome::Complete);
+    }
+    Html(progress(&state))
+}
+
+/// polls one worker for the lifetime of a job, claiming batches until none
+/// are left or the job stops
+async fn run_host(host: String, shared_state: SharedState, job: JobHandle) {
+    let socket = shared_state.lock().await.sockets.get(&host).cloned();
+    if let Some(socket) = socket {
+        return run_socket_host(host, socket, shared_state, job).await
+    }
+    let client = &job.client;
+    // batch handed to this worker that it hasn't reported back yet
+    let mut current: Option<usize> = None;
+    // when `current` was first posted, until the worker starts on it
+    let mut posted_at: Option<Instant> = None;
+    let mut last_claim: Option<Instant> = None;
+    // unreadable updates in a row, for backing off
+    let mut bad_updates: u32 = 0;
+    loop {
+        {
+            let mut state = shared_state.lock().await;
+            if !state.is_current(job.id) {
+                break;
+            }
+            // taken out of the pool, its batch goes to someone else
+            if !state.hosts.contains(&host) {
+                if let Some(batch) = current {
+                    requeue_batch(&mut state, &host, batch);
+                }
+                break;
+            }
+        }
+        let resp = client
+            .get(host.to_string() + "/update")
+            .header(ACCEPT, job.encoding.accept())
+            .send();
+        // a stopped job's requests are abandoned, nothing has been counted
+        // for them yet
+        let Some(resp) = job.unless_stopped(resp).await else { break };
+        let resp = resp.and_then(|r| r.error_for_status());
+        // an unreachable worker keeps its batch, it's sent again once the
+        // worker answers and says it has nothing
+        let resp = match resp {
+            Ok(resp) => {
+                set_health(&mut *shared_state.lock().await, &host, None);
+                resp
+            },
+            Err(e) => {
+                set_health(&mut *shared_state.lock().await, &host, Some(&e));
+                job.pause(job.probe_interval).await;
+                continue;
+            },
+        };
+        let Some(resp) = job.unless_stopped(decode_update(resp)).await else { break };
+        // a proxy's error page or a cut off body is usually gone by the next
+        // poll, so it's asked again rather than given up on
+        let resp = match resp {
+            Ok(resp) => {
+                bad_updates = 0;
+                resp
+            },
+            Err(e) => {
+                let wait = job.parse_retry.saturating_mul(1 << bad_updates.min(16)).min(job.probe_interval);
+                bad_updates += 1;
+                warn!(host, error = %e, retry_in = ?wait, "couldn't read worker's update");
+                job.pause(wait).await;
+                continue;
+            },
+        };
+
+        let restart = matches!(resp, UpdateResp::InProgress { .. })
+            && current.is_some()
+            && shared_state.lock().await.restarts.remove(&host);
+        let req = match resp {
+            // the worker is sent its next batch over the one it's on
+            UpdateResp::InProgress { .. } if restart => {
+                posted_at = None;
+                let mut state = shared_state.lock().await;
+                abandon_batch(&mut state, &host, current.take().unwrap());
+                match claim_batch(&mut state, job.id, &host) {
+                    Claim::Batch(batch_number) => {
+                        current = Some(batch_number);
+                        last_claim = Some(Instant::now());
+                        batch_req(&state, &host, batch_number)
+                    },
+                    Claim::Wait => {
+                        drop(state);
+                        job.pause(CLAIM_RETRY_INTERVAL).await;
+                        continue;
+                    },
+                    Claim::Done => break,
+                }
+            },
+            UpdateResp::InProgress { batch_size, completed } => {
+                posted_at = None;
+                shared_state.lock().await.batch_progress.insert(host.clone(), (completed, batch_size));
+                job.pause(job.poll_interval).await;
+                continue;
+            },
+            UpdateResp::Init if posted_at.is_some_and(|t| t.elapsed() >= job.init_timeout) => {
+                let batch = current.take().unwrap();
+                posted_at = None;
+                release_batch(&mut *shared_state.lock().await, &host, batch);
+                job.pause(job.probe_interval).await;
+                continue;
+            },
+            // the worker may still be picking it up, so it gets a poll's
+            // worth of time before the batch is sent again
+            UpdateResp::Init if current.is_some() => {
+                // it's still this worker's, so it isn't logged as a redispatch
+                job.pause(job.poll_interval).await;
+                batch_req(&*shared_state.lock().await, &host, current.unwrap())
+            },
+            UpdateResp::Init => {
+                job.throttle(last_claim).await;
+                let mut state = shared_state.lock().await;
+                match claim_batch(&mut state, job.id, &host) {
+                    Claim::Batch(batch_number) => {
+                        current = Some(batch_number);
+                        last_claim = Some(Instant::now());
+                        batch_req(&state, &host, batch_number)
+                    },
+                    Claim::Wait => {
+                        drop(state);
+                        job.pause(CLAIM_RETRY_INTERVAL).await;
+                        continue;
+                    },
+                    Claim::Done => break,
+                }
+            },
+            UpdateResp::BatchComplete { keyboards } => {
+                let mut state = shared_state.lock().await;
+                if !state.is_current(job.id) {
+                    break;
+                }
+                // a worker still showing a result we've already taken just
+                // needs new work
+                posted_at = None;
+                if let Some(batch) = current.take() {
+                    job.hand_in(&mut state, &host, batch, keyboards);
+                }
+                if !job.min_dispatch_interval.is_zero() {
+                    drop(state);
+                    job.throttle(last_claim).await;
+                    state = shared_state.lock().await;
+                }
+                match claim_batch(&mut state, job.id, &host) {
+                    Claim::Batch(batch_number) => {
+                        current = Some(batch_number);
+                        last_claim = Some(Instant::now());
+                        batch_req(&state, &host, batch_number)
+                    },
+                    Claim::Wait => {
+                        drop(state);
+                        job.pause(CLAIM_RETRY_INTERVAL).await;
+                        continue;
+                    },
+                    Claim::Done => break,
+                }
+            },
+        };
+        let posted = client
+            .post(host.to_string() + "/new")
+            .json(&req)
+            .send();
+        let Some(posted) = job.unless_stopped(posted).await else { break };
+        let posted = posted.and_then(|r| r.error_for_status());
+        match posted {
+            Ok(_) => {
+                posted_at.get_or_insert_with(Instant::now);
+            },
+            Err(e) => {
+                set_health(&mut *shared_state.lock().await, &host, Some(&e));
+                job.pause(job.probe_interval).await;
+            },
+        }
+    }
+}
+
+/// gives up on a batch `host` was sent but never started, marking the worker
+/// unhealthy and queueing the batch for whoever asks next
+fn release_batch(state: &mut AppState, host: &str, batch: usize) {
+    warn!(host, batch, "worker never started its batch, releasing it");
+    if let Some(inflight) = state.inflight.get_mut(host) {
+        *inflight = inflight.saturating_sub(1);
+    }
+    state.requeued.push(batch);
+    state.unhealthy.insert(host.to_string());
+    state.host_stats.entry(host.to_string()).or_default().failures += 1;
+    log_event(state, EventKind::Stuck { host: host.to_string(), batch });
+}
+
+/// takes back the batch `host` is on when it's restarted from the dashboard,
+/// queueing it for whoever asks next. that's usually `host` itself, starting
+/// the batch over.
+fn abandon_batch(state: &mut AppState, host: &str, batch: usize) {
+    info!(host, batch, "restarting worker's batch");
+    requeue_batch(state, host, batch);
+    log_event(state, EventKind::Restart { host: host.to_string(), batch });
+}
+
+/// frees `host`'s slot for `batch` and queues it for whoever asks next
+fn requeue_batch(state: &mut AppState, host: &str, batch: usize) {
+    if let Some(inflight) = state.inflight.get_mut(host) {
+        *inflight = inflight.saturating_sub(1);
+    }
+    state.batch_progress.remove(host);
+    state.dispatched_at.remove(&batch);
+    state.requeued.push(batch);
+}
+
+/// records whether `host` answered, logging when that changes
+fn set_health(state: &mut AppState, host: &str, error: Option<&reqwest::Error>) {
+    match error {
+        Some(e) => {
+            if state.unhealthy.insert(host.to_string()) {
+                state.host_stats.entry(host.to_string()).or_default().failures += 1;
+                warn!(host, error = %e, "worker unreachable, probing until it's back");
+                log_event(state, EventKind::Unreachable { host: host.to_string(), error: e.to_string() });
+            }
+        },
+        None => {
+            if state.unhealthy.remove(host) {
+                info!(host, "worker reachable again");
+                log_event(state, EventKind::Recovered { host: host.to_string() });
+            }
+        },
+    }
+}
+
+/// drives a websocket worker: batches are pushed as soon as one is claimed and
+/// results arrive without polling
+async fn run_socket_host(host: String, socket: WorkerSocket, shared_state: SharedState, job: JobHandle) {
+    let mut updates = socket.updates.lock().await;
+    // anything queued before this job started belongs to an older one
+    while updates.try_recv().is_ok() {}
+    // batches sent and not reported back yet, workers answer them in order
+    let mut outstanding: VecDeque<usize> = VecDeque::new();
+    let mut last_claim: Option<Instant> = None;
+    loop {
+        {
+            let mut state = shared_state.lock().await;
+            if !state.is_current(job.id) {
+                let _ = socket.tx.send(ControlMsg::Cancel);
+                break;
+            }
+            // taken out of the pool, its batches go to someone else
+            if !state.hosts.contains(&host) {
+                let _ = socket.tx.send(ControlMsg::Cancel);
+                for batch in outstanding.drain(..) {
+                    requeue_batch(&mut state, &host, batch);
+                }
+                break;
+            }
+            if state.restarts.remove(&host) {
+                let _ = socket.tx.send(ControlMsg::Cancel);
+                for batch in outstanding.drain(..) {
+                    abandon_batch(&mut state, &host, batch);
+                }
+            }
+        }
+        // top up to the host's in-flight limit
+        let mut done = false;
+        loop {
+            job.throttle(last_claim).await;
+            let mut state = shared_state.lock().await;
+            match claim_batch(&mut state, job.id, &host) {
+                Claim::Batch(batch_number) => {
+                    outstanding.push_back(batch_number);
+                    last_claim = Some(Instant::now());
+                    let req = batch_req(&state, &host, batch_number);
+                    if socket.tx.send(ControlMsg::Batch(req)).is_err() {
+                        return
+                    }
+                },
+                Claim::Wait => break,
+                Claim::Done => {
+                    done = true;
+                    break;
+                },
+            }
+        }
+        if outstanding.is_empty() {
+            if done {
+                break;
+            }
+            job.pause(CLAIM_RETRY_INTERVAL).await;
+            continue;
+        }
+
+        let update = tokio::select! {
+            update = updates.recv() => update,
+            _ = job.stop.notified() => None,
+            // the limit may have gone up meanwhile
+            _ = tokio::time::sleep(CLAIM_RETRY_INTERVAL) => continue,
+        };
+        let mut state = shared_state.lock().await;
+        if !state.is_current(job.id) {
+            let _ = socket.tx.send(ControlMsg::Cancel);
+            break;
+        }
+        match update {
+            // the socket closed
+            None => break,
+            Some(UpdateResp::BatchComplete { keyboards }) => {
+                if let Some(batch) = outstanding.pop_front() {
+                    job.hand_in(&mut state, &host, batch, keyboards);
+                }
+            },
+            Some(UpdateResp::InProgress { batch_size, completed }) => {
+                state.batch_progress.insert(host.clone(), (completed, batch_size));
+            },
+            Some(UpdateResp::Init) => {},
+        }
+    }
+}
+
+#[cfg(feature = "local-worker")]
+async fn run_local(shared_state: SharedState, job: JobHandle) {
+    let mut running = tokio::task::JoinSet::new();
+    let mut last_claim: Option<Instant> = None;
+    loop {
+        // top up to the in-flight limit
+        let mut done = false;
+        loop {
+            job.throttle(last_claim).await;
+            let mut state = shared_state.lock().await;
+            match claim_batch(&mut state, job.id, LOCAL_HOST) {
+                Claim::Batch(batch_number) => {
+                    last_claim = Some(Instant::now());
+                    let req = batch_req(&state, LOCAL_HOST, batch_number);
+                    let start_layout = state.config.start_layout.clone();
+                    running.spawn_blocking(move || (batch_number, local_worker::run_batch(&req, &start_layout)));
+                },
+                Claim::Wait => break,
+                Claim::Done => {
+                    done = true;
+                    break;
+                },
+            }
+        }
+        if running.is_empty() {
+            if done {
+                break;
+            }
+            job.pause(CLAIM_RETRY_INTERVAL).await;
+            continue;
+        }
+
+        let finished = tokio::select! {
+            finished = running.join_next() => finished,
+            // the limit may have gone up meanwhile
+            _ = tokio::time::sleep(CLAIM_RETRY_INTERVAL) => continue,
+        };
+        let (batch, keyboards) = finished
+            .expect("batches running")
+            .expect("local batch panicked");
+        let mut state = shared_state.lock().await;
+        if !state.is_current(job.id) {
+            break;
+        }
+        job.hand_in(&mut state, LOCAL_HOST, batch, keyboards);
+    }
+}
+
+async fn security_headers<B>(
+    State(shared_state): State<SharedState>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let csp = shared_state.lock().await.config.content_security_policy();
+    let mut resp = next.run(req).await;
+    let headers = resp.headers_mut();
+    if let Ok(csp) = HeaderValue::from_str(&csp) {
+        headers.insert(CONTENT_SECURITY_POLICY, csp);
+    }
+    headers.insert(X_CONTENT_TYPE_OPTIONS, HeaderValue::from_static("nosniff"));
+    headers.insert(X_FRAME_OPTIONS, HeaderValue::from_static("DENY"));
+    headers.insert(REFERRER_POLICY, HeaderValue::from_static("no-referrer"));
+    resp
+}
+
+/// requests need `AUTH_TOKEN` as a bearer token or a `token` query parameter.
+/// without one set the admin endpoints are off, anyone who can reach the
+/// dashboard could shut it down otherwise.
+async fn require_auth<B>(
+    State(shared_state): State<SharedState>,
+    req: Request<B>,
+    next: Next<B>,
+) -> Response {
+    let expected = shared_state.lock().await.config.auth_token.clone();
+    let Some(expected) = expected else {
+        return (StatusCode::FORBIDDEN, "admin endpoints need AUTH_TOKEN set").into_response()
+    };
+    let bearer = req
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let query = Query::<TokenReq>::try_from_uri(req.uri()).ok().and_then(|Query(q)| q.token);
+    if bearer.or(query).as_deref() == Some(expected.as_str()) {
+        next.run(req).await
+    } else {
+        (StatusCode::UNAUTHORIZED, "missing or wrong token").into_response()
+    }
+}
+
+/// escape hatch for a job that claims to be running with nothing behind it
+async fn force_idle(State(shared_state): State<SharedState>) -> Html<String> {
+    let mut state = shared_state.lock().await;
+    let tasks = state.tasks.len();
+    warn!(job = %state.job_name, running = state.is_running(), tasks, "forcing coordinator idle");
+    for task in state.tasks.drain(..) {
+        task.abort();
+    }
+    if state.try_stop() {
+        log_event(&mut state, EventKind::ForcedIdle);
+    }
+    state.stop.notify_waiters();
+    Html(hyprtxt!(
+        "h3" {
+            $: "Idle, aborted "
+            $: tasks
+            $: " worker tasks"
+        }
+    ))
+}
+
+#[derive(Serialize)]
+struct CompactResp {
+    removed: usize,
+    total: usize,
+}
+
+/// rebuilds the results into a fresh vector, best first, keeping the best of
+/// any keyboards with the same layout and trimming past `max_keyboards`
+async fn compact(State(shared_state): State<SharedState>) -> Json<CompactResp> {
+    let mut state = shared_state.lock().await;
+    let before = state.keyboards.len();
+    let mut seen = HashSet::new();
+    let mut keyboards: Vec<Keyboard> = ranked(&state.keyboards, state.config.direction)
+        .into_iter()
+        .filter(|k| seen.insert(layout(k)))
+        .take(state.config.max_keyboards)
+        .cloned()
+        .collect();
+    keyboards.shrink_to_fit();
+    state.keyboards = keyboards;
+    state.rebuild_leaders();
+    let removed = before - state.keyboards.len();
+    info!(removed, total = state.keyboards.len(), "compacted results");
+    Json(CompactResp { removed, total: state.keyboards.len() })
+}
+
+/// log lines as server-sent events from the moment of connecting, each named
+/// by its level. a listener that falls `logs::BUFFER` lines behind is told how
+/// many it missed.
+async fn stream_logs(
+    State(shared_state): State<SharedState>,
+    Query(LogsReq { level }): Query<LogsReq>,
+) -> Result<Sse<impl Stream<Item = Result<sse::Event, Infallible>>>, (StatusCode, String)> {
+    let level: Level = match level.as_deref().map(str::trim) {
+        None | Some("") => Level::INFO,
+        Some(level) => level
+            .parse()
+            .map_err(|_| (StatusCode::BAD_REQUEST, format!("unknown level \"{}\"", level)))?,
+    };
+    let (rx, shutdown) = {
+        let state = shared_state.lock().await;
+        (state.logs.subscribe(), state.shutdown.clone())
+    };
+    // one future for the whole stream, so a shutdown while a line is being
+    // sent isn't missed
+    let closed: Pin<Box<dyn Future<Output = ()> + Send>> = Box::pin(async move { shutdown.notified().await });
+    let events = stream::unfold((rx, closed), move |(mut rx, mut closed)| {
+        async move {
+            loop {
+                let line = tokio::select! {
+                    line = rx.recv() => line,
+                    _ = &mut closed => return None,
+                };
+                let event = match line {
+                    // more verbose levels compare greater
+                    Ok(line) if line.level <= level => sse::Event::default().event(line.level.as_str()).data(line.text),
+                    Ok(_) => continue,
+                    Err(RecvError::Lagged(missed)) => {
+                        sse::Event::default().event("lagged").data(format!("missed {} lines", missed))
+                    },
+                    Err(RecvError::Closed) => return None,
+                };
+                return Some((Ok(event), (rx, closed)))
+            }
+        }
+    });
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
+}
+
+/// everything the coordinator holds, for poking at it while it runs
+async fn debug_state(State(shared_state): State<SharedState>) -> impl IntoResponse {
+    let state = shared_state.lock().await;
+    let body = serde_json::to_string_pretty(&*state).expect("serializable state");
+    ([(CONTENT_TYPE, "application/json")], body)
+}
+
+fn secs_left<S: serde::Serializer>(deadline: &Option<Instant>, s: S) -> Result<S::Ok, S::Error> {
+    deadline
+        .map(|d| d.saturating_duration_since(Instant::now()).as_secs())
+        .serialize(s)
+}
+
+fn secs_ago<S: serde::Serializer>(at: &Option<Instant>, s: S) -> Result<S::Ok, S::Error> {
+    at.map(|at| at.elapsed().as_secs()).serialize(s)
+}
+
+#[derive(Deserialize)]
+struct ShutdownReq {
+    #[serde(default)]
+    reason: String,
+}
+
+/// stops any job, saving its best keyboard, then lets the server exit once
+/// this response is out
+async fn shutdown(
+    State(shared_state): State<SharedState>,
+    Query(ShutdownReq { reason }): Query<ShutdownReq>,
+) -> Html<String> {
+    let mut state = shared_state.lock().await;
+    let reason = if reason.trim().is_empty() { "none given" } else { reason.trim() };
+    warn!(reason, running = state.is_running(), "shutting down");
+    if state.is_running() {
+        finish_job(&mut state, JobOutcome::Stopped);
+    }
+    // log streams never end on their own, and the server waits on them
+    state.shutdown.notify_waiters();
+    state.shutdown.notify_one();
+    Html(hyprtxt!("h3" { $: "Shutting down" }))
+}
+
+/// hands `host` the next unclaimed batch number of the job, following the
+/// dispatch policy
+fn claim_batch(state: &mut AppState, job_id: u64, host: &str) -> Claim {
+    if !state.is_current(job_id) || !state.hosts.iter().any(|h| h == host) {
+        return Claim::Done
+    }
+    if state.dispatched >= state.batches && state.requeued.is_empty() {
+        // a batch still out may come back empty or be released by a stuck
+        // worker, and need handing out again
+        let outstanding = state.dispatched.saturating_sub(state.acked.len());
+        if outstanding > 0 {
+            return Claim::Wait
+        }
+        return Claim::Done
+    }
+    if state.inflight.get(host).copied().unwrap_or(0) >= state.inflight_limit(host) {
+        return Claim::Wait
+    }
+    let claimed = state.claims.get(host).copied().unwrap_or(0);
+    // only a worker of the job that could take a batch right now gets to go
+    // first
+    let can_claim = |h: &String| {
+        let limit = state.inflight_limit(h);
+        state.hosts.contains(h)
+            && !state.unhealthy.contains(h)
+            && limit > 0
+            && state.inflight.get(h).copied().unwrap_or(0) < limit
+    };
+    if state.config.policy == DispatchPolicy::Fair
+        && state.claims.iter().any(|(h, &c)| c < claimed && can_claim(h))
+    {
+        return Claim::Wait
+    }
+    *state.claims.entry(host.to_string()).or_default() += 1;
+    *state.inflight.entry(host.to_string()).or_default() += 1;
+    if let Some(batch) = state.requeued.pop() {
+        state.dispatched_at.insert(batch, Instant::now());
+        log_event(state, EventKind::Redispatch { host: host.to_string(), batch });
+        return Claim::Batch(batch)
+    }
+    if state.logs_batch(state.dispatched) {
+        info!(job = %state.job_name, host, batch = state.dispatched, of = state.batches, "dispatched batch");
+    }
+    log_event(state, EventKind::Dispatch { host: host.to_string(), batch: state.dispatched });
+    state.dispatched_at.insert(state.dispatched, Instant::now());
+    state.dispatched += 1;
+    Claim::Batch(state.dispatched - 1)
+}
+
+/// takes in every finished batch, one at a time and in the order they were
+/// handed in, finishing the job once enough are in. batches from a job that's
+/// no longer running are dropped.
+async fn run_ingest(shared_state: SharedState, mut incoming: mpsc::UnboundedReceiver<Ingest>) {
+    while let Some(Ingest { job_id, host, batch, keyboards }) = incoming.recv().await {
+        let mut state = shared_state.lock().await;
+        if !state.is_current(job_id) {
+            continue;
+        }
+        ingest_batch(&mut state, &host, batch, keyboards);
+        if state.completed >= state.required() {
+            finish_job(&mut state, JobOutcome::Complete);
+        }
+    }
+}
+
+/// records a finished batch from any worker, dropping keyboards that aren't a
+/// permutation of the alphabet or don't have a finite score. a batch that's
+/// already in only counts once, its keyboards replacing the earlier ones if
+/// its best is better.
+fn ingest_batch(state: &mut AppState, host: &str, batch: usize, keyboards: Vec<Keyboard>) {
+    state.last_result = Some(Instant::now());
+    let duplicate = !state.acked.insert(batch);
+    if duplicate && keyboards.is_empty() {
+        warn!(job = %state.job_name, host, batch, "ignoring an empty batch that was already completed");
+        return
+    }
+    if keyboards.is_empty() {
+        state.empty_batches += 1;
+        warn!(job = %state.job_name, host, batch, "worker sent back a batch with no keyboards");
+        if state.config.redispatch_empty && state.retried.insert(batch) {
+            state.acked.remove(&batch);
+            state.requeued.push(batch);
+            return
+        }
+    }
+    if !duplicate {
+        state.completed += 1;
+    }
+    let total = keyboards.len();
+    if let Some(first) = keyboards.first() {
+        if !state.glyph_sets.contains_key(host) {
+            let glyphs = first.keys.iter().flat_map(|k| [k.upper, k.lower]).collect();
+            state.glyph_sets.insert(host.to_string(), glyphs);
+        }
+    }
+    // nothing downstream has to think about nan or infinite scores
+    let (keyboards, unscored): (Vec<Keyboard>, Vec<Keyboard>) = keyboards
+        .into_iter()
+        .partition(|k| k.score.is_finite());
+    if !unscored.is_empty() {
+        warn!(host, rejected = unscored.len(), total, "rejected keyboards with non-finite scores");
+    }
+    let (keyboards, unpermuted): (Vec<Keyboard>, Vec<Keyboard>) = keyboards
+        .into_iter()
+        .partition(|k| is_permutation(k, &state.config.alphabet));
+    if !unpermuted.is_empty() {
+        warn!(host, rejected = unpermuted.len(), total, "rejected keyboards that aren't a permutation of the alphabet");
+    }
+    let (valid, moved): (Vec<Keyboard>, Vec<Keyboard>) = keyboards
+        .into_iter()
+        .partition(|k| state.keeps_fixed(k));
+    if !moved.is_empty() {
+        warn!(host, rejected = moved.len(), total, "rejected keyboards that moved positions the job keeps fixed");
+    }
+    let rejected = total - valid.len();
+    if rejected > 0 {
+        *state.invalid.entry(host.to_string()).or_default() += rejected;
+        state.host_stats.entry(host.to_string()).or_default().rejected += rejected;
+    }
+    let direction = state.config.direction;
+    let batch_best = best(&valid, direction).map(|k| k.score);
+    let prior_best = state.best().cloned();
+    if duplicate {
+        let (earlier, ids) = state.batch_results.get(&batch).cloned().unwrap_or((None, 0..0));
+        let better = match (batch_best, earlier) {
+            (Some(new), Some(old)) => direction.cmp(new, old) == Ordering::Less,
+            (new, old) => new.is_some() && old.is_none(),
+        };
+        if !better {
+            warn!(job = %state.job_name, host, batch, "keeping the earlier result of a batch that came back twice");
+            return
+        }
+        warn!(job = %state.job_name, host, batch, "replacing the earlier result of a batch that came back twice");
+        state.keyboards.retain(|k| !ids.contains(&k.id));
+        state.rebuild_leaders();
+    }
+    if state.logs_batch(batch) {
+        info!(
+            job = %state.job_name,
+            host,
+            batch,
+            keyboards = valid.len(),
+            completed = state.completed,
+            "batch complete",
+        );
+    }
+    log_event(state, EventKind::Complete {
+        host: host.to_string(),
+        batch,
+        keyboards: valid.len(),
+        rejected,
+    });
+    if !valid.is_empty() {
+        let (sum, count) = state.host_scores.entry(host.to_string()).or_default();
+        *sum += valid.iter().map(|k| k.score as f64).sum::<f64>();
+        *count += valid.len();
+    }
+    let first_id = state.next_keyboard_id;
+    for keyboard in valid {
+        state.insert_keyboard(keyboard);
+    }
+    state.batch_results.insert(batch, (batch_best, first_id..state.next_keyboard_id));
+    state.trim_keyboards();
+    if let Some(best) = state.best() {
+        let point = (state.completed, best.score);
+        if prior_best.as_ref().is_some_and(|prior| prior.id != best.id) {
+            state.previous_best = prior_best;
+        }
+        state.convergence.push(point);
+    }
+}
+
+/// adds to the current job's timeline, up to `MAX_JOB_EVENTS`
+fn log_event(state: &mut AppState, kind: EventKind) {
+    let events = state.events.entry(state.job_id).or_default();
+    let kind = match events.len() {
+        n if n >= MAX_JOB_EVENTS => return,
+        n if n == MAX_JOB_EVENTS - 1 => EventKind::Truncated,
+        _ => kind,
+    };
+    let at_ms = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis());
+    events.push(JobEvent { at_ms, kind });
+}
+
+/// the job's event log as ndjson, one event per line
+async fn export_events(
+    State(shared_state): State<SharedState>,
+    Path(job): Path<u64>,
+) -> impl IntoResponse {
+    let state = shared_state.lock().await;
+    let Some(events) = state.events.get(&job) else {
+        return Err((StatusCode::NOT_FOUND, "no event log for that job"))
+    };
+    let body: String = events
+        .iter()
+        .map(|e| serde_json::to_string(e).expect("serializable event") + "\n")
+        .collect();
+    Ok(([(CONTENT_TYPE, "application/x-ndjson")], body))
+}
+
+fn sorted_alphabet(glyphs: &str) -> Vec<char> {
+    let mut alphabet: Vec<char> = glyphs.chars().collect();
+    alphabet.sort_unstable();
+    alphabet
+}
+
+/// whether the keyboard's `upper` glyphs are exactly `alphabet`, which is sorted
+fn is_permutation(keyboard: &Keyboard, alphabet: &[char]) -> bool {
+    let mut glyphs: Vec<char> = keyboard.keys.iter().map(|k| k.upper).collect();
+    glyphs.sort_unstable();
+    glyphs == alphabet
+}
+
+/// marks the current job done and archives it, only the first caller per job
+/// gets to record it
+fn finish_job(state: &mut AppState, outcome: JobOutcome) {
+    if !state.try_stop() {
+        return
+    }
+    state.stop.notify_waiters();
+    log_event(state, EventKind::Finish { outcome: outcome.label() });
+    save_best(state);
+    post_summary(state, outcome);
+    let record = JobRecord {
+        job_id: state.job_id,
+        job_name: state.job_name.clone(),
+        batch_size: state.batch_size,
+        batches: state.batches,
+        completed: state.completed,
+        best_score: state.best().map(|k| k.score),
+        best: state.best().cloned(),
+        tags: state.tags.clone(),
+        seed: state.seed,
+        outcome,
+        runtime_secs: state.started.map_or(0.0, |at| at.elapsed().as_secs_f64()),
+        hosts: host_records(state),
+    };
+    state.history.push(record);
+}
+
+/// every host that took part in the current job, with what it did
+fn host_records(state: &AppState) -> Vec<HostRecord> {
+    let mut hosts: Vec<&String> = state.claims.keys().chain(state.host_stats.keys()).collect();
+    hosts.sort();
+    hosts.dedup();
+    hosts
+        .into_iter()
+        .map(|host| {
+            let stats = state.host_stats.get(host).cloned().unwrap_or_default();
+            HostRecord {
+                host: host.clone(),
+                batches: stats.batches,
+                keyboards: state.host_scores.get(host).map_or(0, |&(_, count)| count),
+                rejected: stats.rejected,
+                failures: stats.failures,
+                avg_batch_secs: (stats.batches > 0).then(|| stats.busy.as_secs_f64() / stats.batches as f64),
+            }
+        })
+        .collect()
+}
+
+/// makes a kept keyboard the one jobs started with `use_seed` build on
+async fn promote_seed(
+    State(shared_state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Html<String>, (StatusCode, String)> {
+    let (path, json) = {
+        let state = shared_state.lock().await;
+        let (_, keyboard) = state
+            .keyboard(id)
+            .ok_or((StatusCode::NOT_FOUND, "no keyboard with that id".to_string()))?;
+        if !keyboard.score.is_finite() || !is_permutation(keyboard, &state.config.alphabet) {
+            return Err((StatusCode::BAD_REQUEST, "keyboard isn't a valid layout".to_string()))
+        }
+        let json = serde_json::to_vec_pretty(keyboard).expect("serializable keyboard");
+        (state.config.seed_file.clone(), json)
+    };
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        tokio::fs::create_dir_all(dir).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    }
+    tokio::fs::write(&path, json).await.map_err(|e| (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+    info!(path = %path.display(), id, "promoted keyboard to seed");
+    Ok(Html(hyprtxt!("p" { $: format!("Saved as the seed in {}", path.display()) })))
+}
+
+/// the keyboard in the seed file, if it's still a layout of `alphabet`
+async fn read_seed(path: &std::path::Path, alphabet: &[char]) -> Result<Keyboard, String> {
+    let json = tokio::fs::read(path)
+        .await
+        .map_err(|e| format!("couldn't read seed {}: {}", path.display(), e))?;
+    let keyboard: Keyboard = serde_json::from_slice(&json)
+        .map_err(|e| format!("seed {} isn't a keyboard: {}", path.display(), e))?;
+    if !is_permutation(&keyboard, alphabet) {
+        return Err(format!("seed {} isn't a permutation of the alphabet", path.display()))
+    }
+    Ok(keyboard)
+}
+
+/// writes the best keyboard to `<output_dir>/<job_name>-best.json` in the
+/// background, as one of the state's tasks so shutdown waits for it
+fn save_best(state: &mut AppState) {
+    let (Some(dir), Some(best)) = (&state.config.output_dir, state.best()) else {
+        return
+    };
+    let name: String = state.job_name
+        .chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' { c } else { '_' })
+        .collect();
+    let path = dir.join(format!("{}-best.json", name));
+    let json = serde_json::to_vec_pretty(best).expect("serializable keyboard");
+    let dir = dir.clone();
+    state.tasks.push(tokio::spawn(async move {
+        let saved = match tokio::fs::create_dir_all(&dir).await {
+            Ok(()) => tokio::fs::write(&path, json).await,
+            Err(e) => Err(e),
+        };
+        match saved {
+            Ok(()) => info!(path = %path.display(), "saved best keyboard"),
+            Err(e) => warn!(path = %path.display(), error = %e, "couldn't save best keyboard"),
+        }
+    }));
+}
+
+#[derive(Serialize)]
+struct JobSummary<'a> {
+    job_id: u64,
+    job_name: &'a str,
+    outcome: JobOutcome,
+    completed: usize,
+    batches: usize,
+    duration_secs: f64,
+    best_score: Option<f32>,
+    best_layout: Option<String>,
+    best: Option<&'a Keyboard>,
+}
+
+/// tells the job's webhook how it went, in the background like `save_best`.
+/// a webhook that can't be reached only gets logged.
+fn post_summary(state: &mut AppState, outcome: JobOutcome) {
+    let Some(url) = state.webhook.clone() else { return };
+    let best = state.best();
+    let summary = JobSummary {
+        job_id: state.job_id,
+        job_name: &state.job_name,
+        outcome,
+        completed: state.completed,
+        batches: state.batches,
+        duration_secs: state.started.map_or(0.0, |at| at.elapsed().as_secs_f64()),
+        best_score: best.map(|k| k.score),
+        best_layout: best.map(layout_string),
+        best,
+    };
+    let sent = state.client.post(url.clone()).json(&summary).send();
+    state.tasks.push(tokio::spawn(async move {
+        match sent.await.and_then(|r| r.error_for_status()) {
+            Ok(_) => info!(url, "posted job summary"),
+            Err(e) => warn!(url, error = %e, "couldn't post job summary"),
+        }
+    }));
+}
+
+/// an http(s) url, trimmed
+fn parse_webhook(input: &str) -> Result<String, String> {
+    let url = input.trim();
+    match reqwest::Url::parse(url) {
+        Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => Ok(url.to_string()),
+        _ => Err(format!("webhook \"{}\" isn't an http url", url)),
+    }
+}
+
+/// `self` is shorthand for the in-process worker
+fn normalize_host(host: &str) -> String {
+    match host.trim() {
+        "self" => LOCAL_HOST.to_string(),
+        host => host.trim_end_matches('/').to_string(),
+    }
+}
+
+/// the first batch request each host would get if the job started now,
+/// without starting it
+async fn preview_dispatch(
+    State(shared_state): State<SharedState>,
+    Query(req): Query<PreviewDispatchReq>,
+) -> Result<Json<Vec<BatchReq>>, (StatusCode, String)> {
+    let bad_request = |e: String| (StatusCode::BAD_REQUEST, e);
+    let schedule = parse_schedule(&req.batch_schedule).map_err(bad_request)?;
+    validate_job(req.batch_size, req.batches, &schedule).map_err(bad_request)?;
+    let pinned = parse_pinned(&req.pinned).map_err(bad_request)?;
+    let seed = parse_seed(&req.seed).map_err(bad_request)?;
+    let mutable = parse_positions(&req.mutable_positions).map_err(bad_request)?;
+    let state = shared_state.lock().await;
+    let hosts = job_hosts(&state, req.group.trim());
+    if hosts.is_empty() {
+        return Err(bad_request("no workers to dispatch to".into()))
+    }
+    let reqs = hosts
+        .into_iter()
+        .map(|host| BatchReq {
+            job_name: req.job_name.clone(),
+            device_name: host,
+            batch_size: schedule.size(req.batch_size, 0),
+            batch_number: 0,
+            pinned: pinned.clone(),
+            mutable_positions: mutable.clone(),
+            seed: batch_seed(seed, 0),
+            seed_layout: None,
+        })
+        .collect();
+    Ok(Json(reqs))
+}
+
+async fn validate_job_params(
+    State(shared_state): State<SharedState>,
+    Query(ValidateJobReq { batch_size, batches, group, batch_schedule }): Query<ValidateJobReq>,
+) -> Html<String> {
+    let parsed = (batch_size.trim().parse(), batches.trim().parse());
+    let (Ok(batch_size), Ok(batches)) = parsed else {
+        return Html(hyprtxt!("p" { $: "enter a batch size and batch count" }))
+    };
+    let checked = parse_schedule(&batch_schedule)
+        .and_then(|schedule| validate_job(batch_size, batches, &schedule).map(|warnings| (warnings, schedule)));
+    let state = shared_state.lock().await;
+    match checked {
+        Err(e) => Html(hyprtxt!("p" {
+            "style"="color: red;"
+            $: escape_html(&e)
+        })),
+        Ok((warnings, schedule)) => Html(hyprtxt!(
+            "div" {
+                "p" {
+                    $: "Total work: "
+                    $: schedule.total(batch_size, batches)
+                    $: " keyboards in "
+                    $: batches
+                    $: " batches"
+                }
+                $: work_estimate(&state, &job_hosts(&state, group.trim()), schedule.total(batch_size, batches))
+                $: warnings
+                    .iter()
+                    .map(|w| hyprtxt!("p" {
+                        "style"="color: orange;"
+                        $: escape_html(w)
                     }))
                     .collect::<Vec<String>>()
                     .concat()
             }
-            "div" {
-                "class"="row"
-                $: &keyboard.keys[13..26]
+        )),
+    }
+}
+
+/// whether the add-worker form's host would work: a url, not already in the
+/// pool, and answering `/update` the way a worker does
+async fn validate_host(
+    State(shared_state): State<SharedState>,
+    Query(ValidateHostReq { host }): Query<ValidateHostReq>,
+) -> Html<String> {
+    let host = normalize_host(&host);
+    let problem = |e: String| Html(hyprtxt!("span" {
+        "style"="color: red;"
+        $: escape_html(&e)
+    }));
+    if host.is_empty() {
+        return Html(String::new())
+    }
+    let client = {
+        let state = shared_state.lock().await;
+        if state.hosts.contains(&host) {
+            return problem(format!("{} is already added", host))
+        }
+        state.client.clone()
+    };
+    if host == LOCAL_HOST {
+        if !cfg!(feature = "local-worker") {
+            return problem("built without the local-worker feature".to_string())
+        }
+    } else {
+        match reqwest::Url::parse(&host) {
+            Ok(parsed) if matches!(parsed.scheme(), "http" | "https") => {},
+            _ => return problem(format!("\"{}\" isn't an http url", host)),
+        }
+        let resp = client
+            .get(host.to_string() + "/update")
+            .timeout(VALIDATE_HOST_TIMEOUT)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status());
+        let update = match resp {
+            Ok(resp) => decode_update(resp).await,
+            Err(e) => return problem(format!("unreachable: {}", e)),
+        };
+        // the error quotes the body, which could be a whole html page
+        if update.is_err() {
+            return problem("answered, but not with a worker update".to_string())
+        }
+    }
+    Html(hyprtxt!("span" {
+        "style"="color: green;"
+        $: "reachable"
+    }))
+}
+
+/// keyboards a second `host` managed in the latest job it finished a batch of
+fn host_rate(state: &AppState, host: &str) -> Option<f64> {
+    state.history
+        .iter()
+        .rev()
+        .flat_map(|job| &job.hosts)
+        .find(|h| h.host == host && h.keyboards > 0 && h.avg_batch_secs.is_some_and(|s| s > 0.0))
+        .map(|h| h.keyboards as f64 / (h.avg_batch_secs.unwrap_or(0.0) * h.batches as f64))
+}
+
+/// roughly how long `hosts` would take over `keyboards`, going by how fast
+/// each was before. flagged once it's past `LONG_JOB`, it's only advice.
+fn work_estimate(state: &AppState, hosts: &[String], keyboards: usize) -> String {
+    if hosts.is_empty() {
+        return String::new()
+    }
+    let rates: Vec<Option<f64>> = hosts.iter().map(|host| host_rate(state, host)).collect();
+    let measured = rates.iter().flatten().count();
+    let rate: f64 = rates.iter().map(|r| r.unwrap_or(DEFAULT_KEYBOARDS_PER_SEC)).sum();
+    let secs = keyboards as f64 / rate;
+    let basis = match measured {
+        0 => format!("assuming {} keyboards/s per worker", DEFAULT_KEYBOARDS_PER_SEC),
+        m if m == hosts.len() => "going by their past jobs".to_string(),
+        m => format!("going by past jobs for {} of them", m),
+    };
+    let estimate = format!(
+        "Roughly {} on {} workers at {:.0} keyboards/s, {}.",
+        format_span(secs),
+        hosts.len(),
+        rate,
+        basis,
+    );
+    if secs > LONG_JOB.as_secs_f64() {
+        hyprtxt!("p" {
+            "style"="color: orange;"
+            $: estimate
+            $: " That's more than the workers can get through in a day, a smaller job may be better."
+        })
+    } else {
+        hyprtxt!("p" { $: estimate })
+    }
+}
+
+/// `secs` in the largest unit that keeps it readable
+fn format_span(secs: f64) -> String {
+    match secs {
+        s if s >= 86_400.0 * 365.0 => format!("{:.1} years", s / (86_400.0 * 365.0)),
+        s if s >= 86_400.0 => format!("{:.1} days", s / 86_400.0),
+        s if s >= 3600.0 => format!("{:.1} hours", s / 3600.0),
+        s if s >= 60.0 => format!("{:.0} minutes", s / 60.0),
+        s => format!("{:.0} seconds", s),
+    }
+}
+
+/// checks job parameters, returning warnings for jobs that can run and an
+/// error for ones that can't
+fn validate_job(batch_size: usize, batches: usize, schedule: &BatchSchedule) -> Result<Vec<String>, String> {
+    if batch_size == 0 {
+        return Err("batch size must be greater than zero".into())
+    }
+    if batches == 0 {
+        return Err("batches must be greater than zero".into())
+    }
+    schedule.covers(batches)?;
+    let Some(total) = schedule.checked_total(batch_size, batches) else {
+        return Err("the batch schedule asks for more keyboards than can be counted".into())
+    };
+    let mut warnings = vec![];
+    if total > LARGE_JOB_KEYBOARDS {
+        warnings.push(format!(
+            "more than {} keyboards total, this job may take a very long time",
+            LARGE_JOB_KEYBOARDS
+        ));
+    }
+    Ok(warnings)
+}
+
+fn job_error(msg: &str) -> Html<String> {
+    Html(hyprtxt!("h1" {
+        "style"="color: red;"
+        $: "ERROR: "
+        $: escape_html(msg)
+    }))
+}
+
+/// `text` as it should read on a page. hyprtxt writes whatever it's given,
+/// so anything a user or worker supplied goes through this first.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// `text` percent-encoded for a query string value
+fn query_escape(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+            b => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+/// a worker's update in whichever encoding it answered with. on failure the
+/// error carries the start of the body, to tell a bad worker from something
+/// in between
+async fn decode_update(resp: reqwest::Response) -> Result<UpdateResp, String> {
+    let msgpack = resp
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .is_some_and(|v| v.contains("msgpack"));
+    let body = resp.bytes().await.map_err(|e| e.to_string())?;
+    let decoded = if msgpack {
+        rmp_serde::from_slice(&body).map_err(|e| e.to_string())
+    } else {
+        serde_json::from_slice(&body).map_err(|e| e.to_string())
+    };
+    decoded.map_err(|e| {
+        let start = String::from_utf8_lossy(&body[..body.len().min(MAX_LOGGED_BODY)]);
+        let more = if body.len() > MAX_LOGGED_BODY { "..." } else { "" };
+        format!("{}, body {:?}{}", e, start, more)
+    })
+}
+
+async fn history(
+    State(shared_state): State<SharedState>,
+    Query(HistoryReq { tag }): Query<HistoryReq>,
+) -> Html<String> {
+    let state = shared_state.lock().await;
+    let tag = tag.filter(|t| !t.is_empty());
+    let rows = state.history
+        .iter()
+        .rev()
+        .filter(|job| tag.as_ref().is_none_or(|t| job.tags.contains(t)))
+        .map(|job| hyprtxt!(
+            "tr" {
+                "td" { $: escape_html(&job.job_name) }
+                "td" {
+                    $: job.completed
+                    $: "/"
+                    $: job.batches
+                }
+                "td" { $: job.batch_size }
+                "td" {
+                    $: job.best_score.map_or("-".to_string(), |s| s.to_string())
+                }
+                "td" { $: tag_chips(&job.tags) }
+                "td" { $: job.seed.map_or("-".to_string(), |s| s.to_string()) }
+                "td" { $: job.outcome.label() }
+                "td" {
+                    "a" {
+                        "href"=format!("/export/events/{}", job.job_id)
+                        $: "events"
+                    }
+                    $: " "
+                    "a" {
+                        "href"=format!("/report/{}", job.job_id)
+                        $: "report"
+                    }
+                }
+            }
+        ) + &host_breakdown(job))
+        .collect::<Vec<String>>()
+        .concat();
+
+    let body = hyprtxt!(
+        "body" {
+            "h1" { $: "History" }
+            "a" {
+                "href"="/"
+                $: "Back"
+            }
+            $: " "
+            "a" {
+                "href"="/hall-of-fame"
+                $: "Hall of Fame"
+            }
+            "form" {
+                "method"="get"
+                "action"="/history"
+                "input" {
+                    "type"="text"
+                    "name"="tag"
+                    "placeholder"="filter by tag"
+                    "value"=escape_html(tag.as_deref().unwrap_or(""))
+                }
+                "button" {
+                    "type"="submit"
+                    $: "Filter"
+                }
+            }
+            "table" {
+                "tr" {
+                    "th" { $: "Job" }
+                    "th" { $: "Batches" }
+                    "th" { $: "Batch Size" }
+                    "th" { $: "Best Score" }
+                    "th" { $: "Tags" }
+                    "th" { $: "Seed" }
+                    "th" { $: "Outcome" }
+                    "th" {}
+                }
+                $: rows
+            }
+        }
+    );
+    Html(["<!DOCTYPE html>".to_string(), head(&state.config), body].join(""))
+}
+
+/// a history row that opens up to show how each host did
+fn host_breakdown(job: &JobRecord) -> String {
+    hyprtxt!(
+        "tr" {
+            "td" {
+                "colspan"="8"
+                "details" {
+                    "summary" {
+                        $: format!("{} hosts, ran {:.0}s", job.hosts.len(), job.runtime_secs)
+                    }
+                    $: host_table(job)
+                }
+            }
+        }
+    )
+}
+
+fn host_table(job: &JobRecord) -> String {
+    let rows = job.hosts
+        .iter()
+        .map(|host| hyprtxt!(
+            "tr" {
+                "td" { $: escape_html(&host.host) }
+                "td" { $: host.batches }
+                "td" { $: host.keyboards }
+                "td" { $: host.rejected }
+                "td" { $: host.failures }
+                "td" {
+                    $: host.avg_batch_secs.map_or("-".to_string(), |s| format!("{:.1}s", s))
+                }
+            }
+        ))
+        .collect::<Vec<String>>()
+        .concat();
+    hyprtxt!(
+        "table" {
+            "tr" {
+                "th" { $: "Host" }
+                "th" { $: "Batches" }
+                "th" { $: "Keyboards" }
+                "th" { $: "Rejected" }
+                "th" { $: "Failures" }
+                "th" { $: "Avg Batch" }
+            }
+            $: rows
+        }
+    )
+}
+
+/// every job this coordinator has run, newest first, the running one on top
+async fn jobs(State(shared_state): State<SharedState>) -> Html<String> {
+    let state = shared_state.lock().await;
+    let row = |job_id: u64, name: &str, status: &str, completed: usize, batches: usize, best: Option<f32>| hyprtxt!(
+        "tr" {
+            "td" { $: escape_html(name) }
+            "td" { $: status }
+            "td" {
+                $: completed
+                $: "/"
+                $: batches
+            }
+            "td" { $: best.map_or("-".to_string(), |s| s.to_string()) }
+            "td" {
+                "a" {
+                    "href"=format!("/update?job={}", job_id)
+                    $: "view"
+                }
+            }
+        }
+    );
+    let running = if state.is_running() {
+        let best = state.best().map(|k| k.score);
+        row(state.job_id, &state.job_name, "running", state.completed, state.batches, best)
+    } else {
+        String::new()
+    };
+    let finished = state.history
+        .iter()
+        .rev()
+        .map(|job| row(job.job_id, &job.job_name, job.outcome.label(), job.completed, job.batches, job.best_score))
+        .collect::<Vec<String>>()
+        .concat();
+
+    let body = hyprtxt!(
+        "body" {
+            "h1" { $: "Jobs" }
+            "a" {
+                "href"="/"
+                $: "Back"
+            }
+            "table" {
+                "tr" {
+                    "th" { $: "Job" }
+                    "th" { $: "Status" }
+                    "th" { $: "Batches" }
+                    "th" { $: "Best Score" }
+                    "th" {}
+                }
+                $: running
+                $: finished
+            }
+        }
+    );
+    Html(["<!DOCTYPE html>".to_string(), head(&state.config), body].join(""))
+}
+
+/// best score over the current job's batches, raw and smoothed with an
+/// exponential moving average weighted by `alpha`
+async fn convergence(
+    State(shared_state): State<SharedState>,
+    Query(ConvergenceReq { alpha }): Query<ConvergenceReq>,
+) -> Html<String> {
+    let state = shared_state.lock().await;
+    let alpha = alpha.filter(|a| *a > 0.0 && *a <= 1.0).unwrap_or(DEFAULT_EMA_ALPHA);
+    let body = hyprtxt!(
+        "body" {
+            "h1" { $: "Convergence" }
+            "a" {
+                "href"="/"
+                $: "Back"
+            }
+            "form" {
+                "method"="get"
+                "action"="/convergence"
+                "label" {
+                    "for"="alpha"
+                    $: "Smoothing "
+                }
+                "input" {
+                    "type"="number"
+                    "step"="0.05"
+                    "min"="0.05"
+                    "max"="1"
+                    "name"="alpha"
+                    "id"="alpha"
+                    "value"=alpha
+                }
+                "button" {
+                    "type"="submit"
+                    $: "Apply"
+                }
+            }
+            $: convergence_chart(&state.convergence, state.batches, alpha)
+        }
+    );
+    Html(["<!DOCTYPE html>".to_string(), head(&state.config), body].join(""))
+}
+
+/// the glyph most keyboards have at each position, shaded by how many agree
+async fn position_heatmap(State(shared_state): State<SharedState>) -> Html<String> {
+    let state = shared_state.lock().await;
+    let consensus = position_consensus(&state.keyboards);
+    let total = state.keyboards.len().max(1);
+    let grid = hyprtxt!(
+        "div" {
+            "class"="keyboard"
+            $: ROWS
+                .iter()
+                .zip(row_offsets(&state.board))
+                .map(|(&(start, end), offset)| hyprtxt!("div" {
+                    "class"="row"
+                    "style"=format!("margin-left: {}em;", offset * KEY_EM)
+                    $: consensus[start..end]
+                        .iter()
+                        .map(|&(glyph, count)| {
+                            let share = count as f32 / total as f32;
+                            hyprtxt!("button" {
+                                "class"="button"
+                                "style"=format!("background: rgba(0, 128, 0, {:.2});", share)
+                                "title"=format!("{} of {} keyboards", count, state.keyboards.len())
+                                $: glyph.map_or(String::new(), |g| escape_html(&g.to_string()))
+                                "br"* {}
+                                $: format!("{:.0}%", share * 100.0)
+                            })
+                        })
+                        .collect::<Vec<String>>()
+                        .concat()
+                }))
+                .collect::<Vec<String>>()
+                .concat()
+        }
+    );
+    let body = hyprtxt!(
+        "body" {
+            "h1" { $: "Position Heatmap" }
+            "a" {
+                "href"="/"
+                $: "Back"
+            }
+            "p" {
+                $: format!("Most common glyph at each position over {} keyboards.", state.keyboards.len())
+            }
+            $: grid
+        }
+    );
+    Html(["<!DOCTYPE html>".to_string(), head(&state.config), body].join(""))
+}
+
+/// the most common `upper` glyph at each position and how many keyboards
+/// have it there, ties going to the glyph that sorts first
+fn position_consensus(keyboards: &[Keyboard]) -> Vec<(Option<char>, usize)> {
+    (0..47)
+        .map(|i| {
+            let mut counts: BTreeMap<char, usize> = BTreeMap::new();
+            for keyboard in keyboards {
+                *counts.entry(keyboard.keys[i].upper).or_default() += 1;
+            }
+            counts
+                .into_iter()
+                .rev()
+                .max_by_key(|&(_, count)| count)
+                .map_or((None, 0), |(glyph, count)| (Some(glyph), count))
+        })
+        .collect()
+}
+
+fn ema(points: &[(usize, f32)], alpha: f32) -> Vec<(usize, f32)> {
+    let mut smoothed: Vec<(usize, f32)> = Vec::with_capacity(points.len());
+    for &(x, y) in points {
+        let y = smoothed.last().map_or(y, |&(_, prev)| alpha * y + (1.0 - alpha) * prev);
+        smoothed.push((x, y));
+    }
+    smoothed
+}
+
+/// svg of the raw points as dots and their moving average as a line
+fn convergence_chart(points: &[(usize, f32)], batches: usize, alpha: f32) -> String {
+    const WIDTH: f32 = 600.0;
+    const HEIGHT: f32 = 300.0;
+    const PAD: f32 = 30.0;
+    if points.is_empty() {
+        return hyprtxt!("p" { $: "No batches in yet" })
+    }
+    let lo = points.iter().map(|p| p.1).fold(f32::INFINITY, f32::min);
+    let hi = points.iter().map(|p| p.1).fold(f32::NEG_INFINITY, f32::max);
+    let span = if hi > lo { hi - lo } else { 1.0 };
+    let x = |batch: usize| PAD + batch as f32 / batches.max(1) as f32 * (WIDTH - 2.0 * PAD);
+    let y = |score: f32| HEIGHT - PAD - (score - lo) / span * (HEIGHT - 2.0 * PAD);
+
+    let dots = points
+        .iter()
+        .map(|&(b, s)| format!("<circle cx=\"{:.1}\" cy=\"{:.1}\" r=\"3\" fill=\"#888\"/>", x(b), y(s)))
+        .collect::<String>();
+    let line = ema(points, alpha)
+        .iter()
+        .map(|&(b, s)| format!("{:.1},{:.1}", x(b), y(s)))
+        .collect::<Vec<String>>()
+        .join(" ");
+    format!(
+        "<svg width=\"{w}\" height=\"{h}\" xmlns=\"http://www.w3.org/2000/svg\">\
+        <rect width=\"{w}\" height=\"{h}\" fill=\"white\" stroke=\"#ccc\"/>\
+        <text x=\"{pad}\" y=\"20\" font-size=\"12\">{hi}</text>\
+        <text x=\"{pad}\" y=\"{bottom}\" font-size=\"12\">{lo}</text>\
+        {dots}\
+        <polyline points=\"{line}\" fill=\"none\" stroke=\"#06c\" stroke-width=\"2\"/>\
+        </svg>",
+        w = WIDTH, h = HEIGHT, pad = PAD, bottom = HEIGHT - 8.0, hi = hi, lo = lo, dots = dots, line = line,
+    )
+}
+
+/// svg of the keyboard drawn the way `png::render` draws it, for pages that
+/// can't link to anything
+fn keyboard_svg(keyboard: &Keyboard, offsets: [f32; 4]) -> String {
+    const KEY: f32 = 40.0;
+    const GAP: f32 = 4.0;
+    const PAD: f32 = 12.0;
+    let widest = ROWS
+        .iter()
+        .zip(offsets)
+        .map(|(&(start, end), offset)| offset + (end - start) as f32)
+        .fold(0.0, f32::max);
+    let width = PAD * 2.0 + widest * KEY + (widest - 1.0) * GAP;
+    let height = PAD * 2.0 + ROWS.len() as f32 * (KEY + GAP) - GAP;
+    let keys = ROWS
+        .iter()
+        .enumerate()
+        .flat_map(|(row, &(start, end))| {
+            keyboard.keys[start..end].iter().enumerate().map(move |(col, key)| {
+                let x = PAD + (offsets[row] + col as f32) * (KEY + GAP);
+                let y = PAD + row as f32 * (KEY + GAP);
+                format!(
+                    "<rect x=\"{x:.1}\" y=\"{y:.1}\" width=\"{k}\" height=\"{k}\" rx=\"5\" fill=\"#3c3c3c\"/>\
+                    <text x=\"{cx:.1}\" y=\"{cy:.1}\" font-size=\"20\" font-family=\"monospace\" fill=\"white\" \
+                    text-anchor=\"middle\" dominant-baseline=\"central\">{glyph}</text>",
+                    x = x, y = y, k = KEY, cx = x + KEY / 2.0, cy = y + KEY / 2.0, glyph = escape_html(&key.upper.to_string()),
+                )
+            })
+        })
+        .collect::<String>();
+    format!(
+        "<svg width=\"{w:.0}\" height=\"{h:.0}\" xmlns=\"http://www.w3.org/2000/svg\">\
+        <rect width=\"{w:.0}\" height=\"{h:.0}\" fill=\"#f5f5f5\"/>{keys}</svg>",
+        w = width, h = height, keys = keys,
+    )
+}
+
+/// the inline stylesheet of `/report/:job`, which loads nothing else
+const REPORT_CSS: &str = "body { font-family: sans-serif; max-width: 60em; margin: 2em auto; color: #222; } \
+    table { border-collapse: collapse; } th, td { border: 1px solid #ccc; padding: 0.3em 0.6em; text-align: left; } \
+    th { background: #eee; } code { background: #f4f4f4; padding: 0.1em 0.3em; }";
+
+/// a finished job as one html file with nothing external, for attaching
+/// somewhere. score stats and the convergence chart are only there for the
+/// latest job, the one whose results are still kept.
+async fn report(
+    State(shared_state): State<SharedState>,
+    Path(job): Path<u64>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let state = shared_state.lock().await;
+    let Some(record) = state.history.iter().rev().find(|r| r.job_id == job) else {
+        return Err((StatusCode::NOT_FOUND, "no finished job with that id"))
+    };
+    let latest = job == state.job_id;
+    let best = match &record.best {
+        Some(best) => hyprtxt!(
+            "div" {
+                "p" {
+                    $: format!("Best score {} (keyboard {}): ", best.score, best.id)
+                    "code" { $: escape_html(&layout_string(best)) }
+                }
+                $: keyboard_svg(best, row_offsets(&state.board))
+            }
+        ),
+        None => hyprtxt!("p" { $: "No keyboards came in." }),
+    };
+    let stats = if !latest {
+        hyprtxt!("p" { $: "Only the latest job's keyboards are kept, so there are no score stats for this one." })
+    } else if state.keyboards.is_empty() {
+        hyprtxt!("p" { $: "No keyboards kept." })
+    } else {
+        let scores: Vec<f32> = ranked(&state.keyboards, state.config.direction).iter().map(|k| k.score).collect();
+        let mean = scores.iter().map(|&s| s as f64).sum::<f64>() / scores.len() as f64;
+        hyprtxt!(
+            "table" {
+                "tr" {
+                    "th" { $: "Keyboards" }
+                    "th" { $: "Best" }
+                    "th" { $: "Median" }
+                    "th" { $: "Mean" }
+                    "th" { $: "Worst" }
+                }
+                "tr" {
+                    "td" { $: scores.len() }
+                    "td" { $: scores[0] }
+                    "td" { $: scores[scores.len() / 2] }
+                    "td" { $: format!("{:.3}", mean) }
+                    "td" { $: scores[scores.len() - 1] }
+                }
+            }
+        )
+    };
+    let chart = if latest {
+        convergence_chart(&state.convergence, record.batches, DEFAULT_EMA_ALPHA)
+    } else {
+        hyprtxt!("p" { $: "Only kept for the latest job." })
+    };
+    let title = format!("{} (job {})", escape_html(&record.job_name), record.job_id);
+    let head = hyprtxt!(
+        "head" {
+            "meta"* { "charset"="UTF-8" }
+            "title" { $: title }
+            "style" { $: REPORT_CSS }
+        }
+    );
+    let body = hyprtxt!(
+        "body" {
+            "h1" { $: title }
+            "p" {
+                $: format!(
+                    "{}, {} of {} batches of {} in {}.",
+                    record.outcome.label(),
+                    record.completed,
+                    record.batches,
+                    record.batch_size,
+                    format_span(record.runtime_secs),
+                )
+                $: record.seed.map_or(String::new(), |s| format!(" Seed {}.", s))
+                $: if record.tags.is_empty() { String::new() } else { format!(" Tags: {}.", escape_html(&record.tags.join(", "))) }
+            }
+            "h2" { $: "Best Keyboard" }
+            $: best
+            "h2" { $: "Scores" }
+            $: stats
+            "h2" { $: "Convergence" }
+            $: chart
+            "h2" { $: "Hosts" }
+            $: host_table(record)
+        }
+    );
+    Ok((
+        [
+            (CONTENT_TYPE, "text/html; charset=utf-8".to_string()),
+            (CONTENT_DISPOSITION, format!("attachment; filename=\"job-{}-report.html\"", job)),
+        ],
+        ["<!DOCTYPE html>".to_string(), head, body].join(""),
+    ))
+}
+
+/// every distinct layout that was the best of some job, best first. a layout
+/// several jobs found is listed once, under its best score.
+async fn hall_of_fame(State(shared_state): State<SharedState>) -> Html<String> {
+    let state = shared_state.lock().await;
+    let mut entries: Vec<(&Keyboard, Vec<&str>)> = vec![];
+    for job in &state.history {
+        let Some(best) = &job.best else { continue };
+        match entries.iter_mut().find(|(k, _)| same_layout(k, best)) {
+            Some((k, jobs)) => {
+                if state.config.direction.cmp(best.score, k.score) == Ordering::Less {
+                    *k = best;
+                }
+                jobs.push(&job.job_name);
+            },
+            None => entries.push((best, vec![&job.job_name])),
+        }
+    }
+    entries.sort_by(|a, b| state.config.direction.cmp(a.0.score, b.0.score));
+
+    let rows = entries
+        .iter()
+        .enumerate()
+        .map(|(rank, (k, jobs))| hyprtxt!(
+            "tr" {
+                "td" { $: rank }
+                "td" { $: k.score }
+                "td" { $: escape_html(&jobs.join(", ")) }
+                "td" {
+                    "code" { $: escape_html(&layout_string(k)) }
+                }
+            }
+        ))
+        .collect::<String>();
+    let body = hyprtxt!(
+        "body" {
+            "h1" { $: "Hall of Fame" }
+            "a" {
+                "href"="/history"
+                $: "History"
+            }
+            "table" {
+                "tr" {
+                    "th" { $: "Rank" }
+                    "th" { $: "Score" }
+                    "th" { $: "Jobs" }
+                    "th" { $: "Layout" }
+                }
+                $: rows
+            }
+        }
+    );
+    Html(["<!DOCTYPE html>".to_string(), head(&state.config), body].join(""))
+}
+
+/// same glyphs in the same places on both layers, whatever the score
+fn same_layout(a: &Keyboard, b: &Keyboard) -> bool {
+    a.keys.iter().zip(&b.keys).all(|(a, b)| a.upper == b.upper && a.lower == b.lower)
+}
+
+fn tag_chips(tags: &[String]) -> String {
+    tags.iter()
+        .map(|t| hyprtxt!("a" {
+            "class"="chip"
+            "href"=format!("/history?tag={}", escape_html(&query_escape(t)))
+            $: escape_html(t)
+        }))
+        .collect::<Vec<String>>()
+        .concat()
+}
+
+/// a share of batches in (0, 1], blank meaning all of them
+fn parse_fraction(input: &str) -> Result<f32, String> {
+    if input.trim().is_empty() {
+        return Ok(1.0)
+    }
+    match input.trim().parse::<f32>() {
+        Ok(f) if f > 0.0 && f <= 1.0 => Ok(f),
+        _ => Err(format!("completion fraction \"{}\" must be between 0 and 1", input)),
+    }
+}
+
+/// whole seconds, blank meaning no limit
+fn parse_runtime(input: &str) -> Result<Option<Duration>, String> {
+    if input.trim().is_empty() {
+        return Ok(None)
+    }
+    match input.trim().parse::<u64>() {
+        Ok(secs) if secs > 0 => Ok(Some(Duration::from_secs(secs))),
+        _ => Err(format!("max runtime \"{}\" must be a positive number of seconds", input)),
+    }
+}
+
+/// distinct positions on the board, sorted
+fn parse_positions(input: &str) -> Result<Vec<usize>, String> {
+    let mut positions = vec![];
+    for entry in input.split(|c: char| c == ',' || c.is_whitespace()).filter(|e| !e.is_empty()) {
+        let pos: usize = entry
+            .parse()
+            .map_err(|_| format!("position \"{}\" is not a number", entry))?;
+        if pos >= 47 {
+            return Err(format!("position {} is out of range", pos))
+        }
+        if positions.contains(&pos) {
+            return Err(format!("position {} is listed twice", pos))
+        }
+        positions.push(pos);
+    }
+    positions.sort_unstable();
+    Ok(positions)
+}
+
+/// comma or space separated sizes, one per batch, or `x` and a factor to
+/// grow each batch from the last, e.g. `x1.5`
+fn parse_schedule(input: &str) -> Result<BatchSchedule, String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(BatchSchedule::Constant)
+    }
+    if let Some(factor) = input.strip_prefix('x') {
+        return match factor.trim().parse::<f32>() {
+            Ok(factor) if factor.is_finite() && factor > 0.0 => Ok(BatchSchedule::Growth(factor)),
+            _ => Err(format!("growth factor \"{}\" must be a positive number", factor)),
+        }
+    }
+    let mut sizes = vec![];
+    for entry in input.split(|c: char| c == ',' || c.is_whitespace()).filter(|e| !e.is_empty()) {
+        match entry.parse::<usize>() {
+            Ok(size) if size > 0 => sizes.push(size),
+            _ => return Err(format!("batch size \"{}\" must be a whole number above zero", entry)),
+        }
+    }
+    Ok(BatchSchedule::Sizes(sizes))
+}
+
+fn parse_seed(input: &str) -> Result<Option<u64>, String> {
+    if input.trim().is_empty() {
+        return Ok(None)
+    }
+    match input.trim().parse::<u64>() {
+        Ok(seed) => Ok(Some(seed)),
+        _ => Err(format!("seed \"{}\" must be a whole number", input)),
+    }
+}
+
+/// comma separated, blanks and duplicates dropped
+fn parse_tags(input: &str) -> Vec<String> {
+    let mut tags: Vec<String> = vec![];
+    for tag in input.split(',').map(str::trim).filter(|t| !t.is_empty()) {
+        if !tags.iter().any(|t| t == tag) {
+            tags.push(tag.to_string());
+        }
+    }
+    tags
+}
+
+fn batch_req(state: &AppState, host: &str, batch_number: usize) -> BatchReq {
+    BatchReq {
+        job_name: state.job_name.clone(),
+        device_name: host.to_string(),
+        batch_size: state.schedule.size(state.batch_size, batch_number),
+        batch_number,
+        pinned: state.pinned.clone(),
+        mutable_positions: state.mutable.clone(),
+        seed: batch_seed(state.seed, batch_number),
+        seed_layout: state.seed_layout.clone(),
+    }
+}
+
+fn batch_seed(seed: Option<u64>, batch_number: usize) -> Option<u64> {
+    seed.map(|seed| seed.wrapping_add(batch_number as u64))
+}
+
+/// the hosts a job for `group` would run on, every host if it's empty
+fn job_hosts(state: &AppState, group: &str) -> Vec<String> {
+    state.hosts
+        .iter()
+        .filter(|host| group.is_empty() || state.groups.get(*host).is_some_and(|g| g == group))
+        .cloned()
+        .collect()
+}
+
+/// pins as `parse_pinned` reads them, the inverse of it
+fn pinned_string(pinned: &[(usize, char)]) -> String {
+    pinned.iter().map(|(pos, glyph)| format!("{}:{}", pos, glyph)).collect::<Vec<String>>().join(" ")
+}
+
+/// the start form's pinned keys, filled in with the locks so far
+fn pinned_input(locks: &[(usize, char)]) -> String {
+    hyprtxt!(
+        "input" {
+            "type"="text"
+            "name"="pinned"
+            "id"="pinned"
+            "placeholder"="0:` 1:1 2:2"
+            "value"=escape_html(&pinned_string(locks))
+        }
+    )
+}
+
+/// locks the glyph at `pos` of a keyboard in place for the next job, or
+/// unlocks whatever was locked there
+async fn toggle_lock(
+    State(shared_state): State<SharedState>,
+    Form(ToggleLockReq { id, pos }): Form<ToggleLockReq>,
+) -> Result<Html<String>, (StatusCode, &'static str)> {
+    let mut state = shared_state.lock().await;
+    if pos >= 47 {
+        return Err((StatusCode::BAD_REQUEST, "position is out of range"))
+    }
+    let glyph = state.keyboard(id).ok_or((StatusCode::NOT_FOUND, "no keyboard with that id"))?.1.keys[pos].upper;
+    match state.locks.iter().position(|(p, _)| *p == pos) {
+        Some(i) => {
+            state.locks.remove(i);
+        }
+        None => {
+            state.locks.push((pos, glyph));
+            state.locks.sort_unstable();
+        }
+    }
+    Ok(Html(pinned_input(&state.locks)))
+}
+
+/// parses whitespace separated `position:glyph` pairs, e.g. `0:` 13:q`
+fn parse_pinned(input: &str) -> Result<Vec<(usize, char)>, String> {
+    let mut pinned: Vec<(usize, char)> = vec![];
+    for entry in input.split_whitespace() {
+        let (pos, glyph) = entry
+            .split_once(':')
+            .ok_or(format!("pinned key \"{}\" is not position:glyph", entry))?;
+        let pos: usize = pos
+            .parse()
+            .map_err(|_| format!("pinned position \"{}\" is not a number", pos))?;
+        let mut chars = glyph.chars();
+        let glyph = match (chars.next(), chars.next()) {
+            (Some(c), None) => c,
+            _ => return Err(format!("pinned glyph \"{}\" must be one character", glyph)),
+        };
+        if pos >= 47 {
+            return Err(format!("pinned position {} is out of range", pos))
+        }
+        if pinned.iter().any(|(p, _)| *p == pos) {
+            return Err(format!("position {} is pinned twice", pos))
+        }
+        pinned.push((pos, glyph));
+    }
+    Ok(pinned)
+}
+
+async fn export_png(State(shared_state): State<SharedState>) -> impl IntoResponse {
+    let state = shared_state.lock().await;
+    match state.best() {
+        Some(keyboard) => Ok(([(CONTENT_TYPE, "image/png")], png::render(keyboard, row_offsets(&state.board)))),
+        None => Err((StatusCode::NOT_FOUND, "no keyboards yet")),
+    }
+}
+
+async fn export_qmk_best(State(shared_state): State<SharedState>) -> impl IntoResponse {
+    let best = {
+        let state = shared_state.lock().await;
+        state.best().map(|k| k.id)
+    };
+    export_qmk(State(shared_state), Path(best.unwrap_or(u64::MAX))).await
+}
+
+async fn export_qmk(
+    State(shared_state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    let state = shared_state.lock().await;
+    match state.keyboard(id) {
+        Some((index, keyboard)) => Ok((
+            [
+                (CONTENT_TYPE, "text/plain; charset=utf-8"),
+                (CONTENT_DISPOSITION, "attachment; filename=\"keymap.c\""),
+            ],
+            qmk_keymap(keyboard, &state.job_name, index, &state.board),
+        )),
+        None => Err((StatusCode::NOT_FOUND, "no keyboard with that id")),
+    }
+}
+
+/// scores a leaderboard entry with our own effort model, so it can be compared
+/// with what the worker reported
+async fn rescore(
+    State(shared_state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Html<String>, (StatusCode, &'static str)> {
+    let state = shared_state.lock().await;
+    let (index, entry) = state.keyboard(id).ok_or((StatusCode::NOT_FOUND, "no keyboard with that id"))?;
+    let local = effort::score_layout(entry, effort::FreqTable::english());
+    let body = hyprtxt!(
+        "body" {
+            "h1" {
+                $: "Rank "
+                $: index
+            }
+            "a" {
+                "href"="/"
+                $: "Back"
+            }
+            $: keyboard(entry, &state.pinned, &state.board, Labels::Glyph)
+            "table" {
+                "tr" {
+                    "th" { $: "Worker Score" }
+                    "td" { $: entry.score }
+                }
+                "tr" {
+                    "th" { $: "Coordinator Score" }
+                    "td" { $: local }
+                }
+            }
+            "p" {
+                $: "The coordinator's effort model is an independent check. Its scale \
+                    differs from most workers', so compare how keyboards rank rather \
+                    than the raw numbers."
+            }
+        }
+    );
+    Ok(Html(["<!DOCTYPE html>".to_string(), head(&state.config), body].join("")))
+}
+
+/// how the results stack up against one of them, usually an imported
+/// reference layout like qwerty
+async fn vs_baseline(
+    State(shared_state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Html<String>, (StatusCode, &'static str)> {
+    let state = shared_state.lock().await;
+    let (_, baseline) = state.keyboard(id).ok_or((StatusCode::NOT_FOUND, "no keyboard with that id"))?;
+    let direction = state.config.direction;
+    let margins: Vec<f32> = state.keyboards
+        .iter()
+        .filter(|k| direction.cmp(k.score, baseline.score) == Ordering::Less)
+        .map(|k| (k.score - baseline.score).abs())
+        .collect();
+    let others = state.keyboards.len() - 1;
+    let summary = if margins.is_empty() {
+        hyprtxt!("p" {
+            $: format!("None of the other {} keyboards beat it.", others)
+        })
+    } else {
+        hyprtxt!(
+            "table" {
+                "tr" {
+                    "th" { $: "Better" }
+                    "td" {
+                        $: format!(
+                            "{} of {} ({:.1}%)",
+                            margins.len(),
+                            others,
+                            margins.len() as f32 * 100.0 / others as f32,
+                        )
+                    }
+                }
+                "tr" {
+                    "th" { $: "Best Margin" }
+                    "td" { $: margins.iter().copied().fold(0.0, f32::max) }
+                }
+                "tr" {
+                    "th" { $: "Average Margin" }
+                    "td" { $: margins.iter().sum::<f32>() / margins.len() as f32 }
+                }
+            }
+        )
+    };
+    let body = hyprtxt!(
+        "body" {
+            "h1" {
+                $: "Results vs "
+                $: baseline.score
+            }
+            "a" {
+                "href"="/"
+                $: "Back"
+            }
+            $: keyboard(baseline, &state.pinned, &state.board, Labels::Glyph)
+            $: summary
+        }
+    );
+    Ok(Html(["<!DOCTYPE html>".to_string(), head(&state.config), body].join("")))
+}
+
+/// a keyboard's ancestors by `parent_id`, as far back as they're still kept
+async fn lineage(
+    State(shared_state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Html<String>, (StatusCode, &'static str)> {
+    let state = shared_state.lock().await;
+    let (_, entry) = state.keyboard(id).ok_or((StatusCode::NOT_FOUND, "no keyboard with that id"))?;
+    let find = |id: u64| {
+        state.keyboards.iter().chain(&state.favorites).find(|k| k.id == id)
+    };
+    let mut chain = vec![entry];
+    let mut seen = HashSet::from([entry.id]);
+    let mut missing = None;
+    while let Some(parent) = chain.last().and_then(|k| k.parent_id) {
+        if chain.len() >= MAX_LINEAGE || !seen.insert(parent) {
+            break;
+        }
+        match find(parent) {
+            Some(keyboard) => chain.push(keyboard),
+            None => {
+                missing = Some(parent);
+                break;
+            },
+        }
+    }
+    let rows = chain
+        .iter()
+        .enumerate()
+        .map(|(i, k)| hyprtxt!(
+            "tr" {
+                "td" {
+                    "a" {
+                        "href"=format!("/keyboard/{}", k.id)
+                        $: k.id
+                    }
+                }
+                "td" { $: k.generation.map_or("-".to_string(), |g| g.to_string()) }
+                "td" { $: k.score }
+                "td" {
+                    $: match chain.get(i + 1) {
+                        Some(parent) => {
+                            let changed = changed_positions(parent, k);
+                            format!("{} ({:+})", changed.len(), k.score - parent.score)
+                        },
+                        None => "-".to_string(),
+                    }
+                }
+                "td" { "code" { $: escape_html(&layout_string(k)) } }
+            }
+        ))
+        .collect::<Vec<String>>()
+        .concat();
+    let body = hyprtxt!(
+        "body" {
+            "h1" {
+                $: "Lineage of "
+                $: entry.id
+            }
+            "a" {
+                "href"=format!("/keyboard/{}", entry.id)
+                $: "Back"
+            }
+            "table" {
+                "class"="leaderboard"
+                "tr" {
+                    "th" { $: "Keyboard" }
+                    "th" { $: "Generation" }
+                    "th" { $: "Score" }
+                    "th" { $: "Changed From Parent" }
+                    "th" { $: "Layout" }
+                }
+                $: rows
+            }
+            $: match missing {
+                Some(parent) => hyprtxt!("p" { $: format!("Parent {} isn't kept any more.", parent) }),
+                None => String::new(),
+            }
+        }
+    );
+    Ok(Html(["<!DOCTYPE html>".to_string(), head(&state.config), body].join("")))
+}
+
+/// both layers of a leaderboard entry
+async fn keyboard_page(
+    State(shared_state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Html<String>, (StatusCode, &'static str)> {
+    let state = shared_state.lock().await;
+    let (index, entry) = state.keyboard(id).ok_or((StatusCode::NOT_FOUND, "no keyboard with that id"))?;
+    let body = hyprtxt!(
+        "body" {
+            "h1" {
+                $: "Rank "
+                $: index
+            }
+            "a" {
+                "href"="/"
+                $: "Back"
+            }
+            "p" {
+                $: "Score "
+                $: entry.score
+            }
+            "button" {
+                "hx-post"=format!("/promote-seed/{}", entry.id)
+                "hx-swap"="outerHTML"
+                $: "Use as Seed"
+            }
+            $: match entry.parent_id {
+                Some(_) => hyprtxt!(
+                    "a" {
+                        "href"=format!("/lineage/{}", entry.id)
+                        $: "Lineage"
+                    }
+                ),
+                None => String::new(),
+            }
+            $: keyboard_layers(entry, &state.pinned, &state.board)
+        }
+    );
+    Ok(Html(["<!DOCTYPE html>".to_string(), head(&state.config), body].join("")))
+}
+
+async fn export_kle(
+    State(shared_state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    let state = shared_state.lock().await;
+    match state.keyboard(id) {
+        Some((_, keyboard)) => Ok(Json(kle_layout(keyboard, &state.board))),
+        None => Err((StatusCode::NOT_FOUND, "no keyboard with that id")),
+    }
+}
+
+async fn export_string(
+    State(shared_state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> impl IntoResponse {
+    let state = shared_state.lock().await;
+    match state.keyboard(id) {
+        Some((_, keyboard)) => Ok((
+            [(CONTENT_TYPE, "text/plain; charset=utf-8")],
+            layout_string(keyboard),
+        )),
+        None => Err((StatusCode::NOT_FOUND, "no keyboard with that id")),
+    }
+}
+
+#[derive(Serialize)]
+struct VersionResp {
+    version: &'static str,
+    protocol: u32,
+}
+
+async fn version() -> Json<VersionResp> {
+    Json(VersionResp {
+        version: env!("CARGO_PKG_VERSION"),
+        protocol: PROTOCOL_VERSION,
+    })
+}
+
+#[derive(Serialize)]
+struct GlyphsResp {
+    glyphs: Vec<char>,
+    invalid: Vec<InvalidKeyboard>,
+}
+
+/// a keyboard that isn't a permutation of `GlyphsResp::glyphs`
+#[derive(Serialize)]
+struct InvalidKeyboard {
+    id: u64,
+    missing: Vec<char>,
+    duplicated: Vec<char>,
+}
+
+/// every `upper` glyph seen across the results, and the keyboards that don't
+/// use each of them exactly once
+async fn glyphs(State(shared_state): State<SharedState>) -> Json<GlyphsResp> {
+    let state = shared_state.lock().await;
+    let keyboards = ranked(&state.keyboards, state.config.direction);
+    let glyphs: BTreeSet<char> = keyboards
+        .iter()
+        .flat_map(|k| k.keys.iter().map(|key| key.upper))
+        .collect();
+
+    let invalid = keyboards
+        .iter()
+        .filter_map(|keyboard| {
+            let mut counts = BTreeMap::new();
+            for key in &keyboard.keys {
+                *counts.entry(key.upper).or_insert(0) += 1;
+            }
+            let missing: Vec<char> = glyphs.iter().filter(|g| !counts.contains_key(g)).copied().collect();
+            let duplicated: Vec<char> = counts.iter().filter(|(_, &n)| n > 1).map(|(&g, _)| g).collect();
+            (!missing.is_empty() || !duplicated.is_empty())
+                .then_some(InvalidKeyboard { id: keyboard.id, missing, duplicated })
+        })
+        .collect();
+
+    Json(GlyphsResp { glyphs: glyphs.into_iter().collect(), invalid })
+}
+
+#[derive(Serialize)]
+struct KeyboardsPage {
+    total: usize,
+    offset: usize,
+    keyboards: Vec<Keyboard>,
+}
+
+/// a slice of the ranked results, best first
+async fn keyboards_page(
+    State(shared_state): State<SharedState>,
+    Query(PageReq { offset, limit, precision }): Query<PageReq>,
+) -> Response {
+    let limit = limit.unwrap_or(DEFAULT_PAGE);
+    if limit == 0 || limit > MAX_PAGE {
+        let msg = format!("limit must be between 1 and {}", MAX_PAGE);
+        return (StatusCode::BAD_REQUEST, msg).into_response()
+    }
+    if let Err(e) = check_precision(precision) {
+        return (StatusCode::BAD_REQUEST, e).into_response()
+    }
+    let state = shared_state.lock().await;
+    let total = state.keyboards.len();
+    if offset > total {
+        let msg = format!("offset is past the last of {} keyboards", total);
+        return (StatusCode::BAD_REQUEST, msg).into_response()
+    }
+    let keyboards = ranked(&state.keyboards, state.config.direction)
+        .into_iter()
+        .skip(offset)
+        .take(limit)
+        .map(|k| Keyboard { score: rounded(k.score, precision), ..k.clone() })
+        .collect();
+    Json(KeyboardsPage { total, offset, keyboards }).into_response()
+}
+
+/// just the score of every kept keyboard, for plotting
+async fn scores(
+    State(shared_state): State<SharedState>,
+    Query(ScoresReq { order }): Query<ScoresReq>,
+) -> Json<Vec<f32>> {
+    let state = shared_state.lock().await;
+    let scores = match order {
+        ScoreOrder::Ranked => ranked(&state.keyboards, state.config.direction).iter().map(|k| k.score).collect(),
+        ScoreOrder::Inserted => {
+            // trimming shuffles `keyboards`, ids still go up as they come in
+            let mut inserted: Vec<&Keyboard> = state.keyboards.iter().collect();
+            inserted.sort_unstable_by_key(|k| k.id);
+            inserted.iter().map(|k| k.score).collect()
+        },
+    };
+    Json(scores)
+}
+
+/// every kept keyboard along with what they were checked against, as
+/// `/export/json` writes it and `/merge` reads it
+#[derive(Serialize, Deserialize)]
+struct ResultSet {
+    job_name: String,
+    alphabet: String,
+    direction: ScoreDirection,
+    keyboards: Vec<Keyboard>,
+}
+
+#[derive(Serialize)]
+struct MergeResp {
+    merged: usize,
+    duplicates: usize,
+    invalid: usize,
+    total: usize,
+}
+
+/// all the results, best first, for `/merge` on another coordinator
+async fn export_json(State(shared_state): State<SharedState>) -> Json<ResultSet> {
+    let state = shared_state.lock().await;
+    Json(ResultSet {
+        job_name: state.job_name.clone(),
+        alphabet: state.config.alphabet.iter().collect(),
+        direction: state.config.direction,
+        keyboards: ranked(&state.keyboards, state.config.direction).into_iter().cloned().collect(),
+    })
+}
+
+/// adds another coordinator's results to ours. keyboards already here are
+/// skipped, as are ones that wouldn't pass ingestion, then the worst are
+/// trimmed past `max_keyboards`.
+async fn merge(
+    State(shared_state): State<SharedState>,
+    Json(set): Json<ResultSet>,
+) -> Result<Json<MergeResp>, (StatusCode, String)> {
+    let mut state = shared_state.lock().await;
+    if sorted_alphabet(&set.alphabet) != state.config.alphabet {
+        return Err((StatusCode::BAD_REQUEST, "alphabet doesn't match this coordinator's".into()))
+    }
+    if set.direction != state.config.direction {
+        return Err((StatusCode::BAD_REQUEST, "score direction doesn't match this coordinator's".into()))
+    }
+    let mut seen: HashSet<Vec<(char, char)>> = state.keyboards.iter().map(layout).collect();
+    let (mut merged, mut duplicates, mut invalid) = (0, 0, 0);
+    for keyboard in set.keyboards {
+        if !keyboard.score.is_finite() || !is_permutation(&keyboard, &state.config.alphabet) {
+            invalid += 1;
+        } else if !seen.insert(layout(&keyboard)) {
+            duplicates += 1;
+        } else {
+            state.insert_keyboard(keyboard);
+            merged += 1;
+        }
+    }
+    state.trim_keyboards();
+    info!(from = %set.job_name, merged, duplicates, invalid, "merged results");
+    Ok(Json(MergeResp { merged, duplicates, invalid, total: state.keyboards.len() }))
+}
+
+/// how a result set was produced, so it can be recorded alongside an export
+#[derive(Serialize)]
+struct Manifest<'a> {
+    job_id: u64,
+    job_name: &'a str,
+    running: bool,
+    outcome: Option<JobOutcome>,
+    batch_size: usize,
+    batch_schedule: &'a BatchSchedule,
+    batches: usize,
+    completed: usize,
+    min_completion: f32,
+    max_runtime_secs: Option<u64>,
+    pinned: &'a [(usize, char)],
+    mutable_positions: &'a [usize],
+    tags: &'a [String],
+    seed: Option<u64>,
+    board: &'a str,
+    direction: ScoreDirection,
+    alphabet: String,
+    workers: Vec<&'a String>,
+    started_at_ms: Option<u128>,
+    generated_at_ms: u128,
+    keyboards: usize,
+    /// of the keyboards serialized as a json array, best first, the same way
+    /// `/api/keyboards` writes them
+    sha256: String,
+}
+
+/// provenance of the latest job's results, the only ones that are kept
+async fn export_manifest(
+    State(shared_state): State<SharedState>,
+    Path(job): Path<u64>,
+) -> Result<impl IntoResponse, (StatusCode, &'static str)> {
+    let state = shared_state.lock().await;
+    if job == 0 || job != state.job_id {
+        return Err((StatusCode::NOT_FOUND, "only the latest job's results are kept"))
+    }
+    let ranked = ranked(&state.keyboards, state.config.direction);
+    let json = serde_json::to_vec(&ranked).expect("serializable keyboards");
+    let mut workers: Vec<&String> = state.claims.keys().collect();
+    workers.sort();
+    let started_at_ms = state.events
+        .get(&job)
+        .and_then(|events| events.iter().find(|e| matches!(e.kind, EventKind::Start { .. })))
+        .map(|e| e.at_ms);
+    let manifest = Manifest {
+        job_id: job,
+        job_name: &state.job_name,
+        running: state.is_running(),
+        outcome: state.history.iter().rev().find(|r| r.job_id == job).map(|r| r.outcome),
+        batch_size: state.batch_size,
+        batch_schedule: &state.schedule,
+        batches: state.batches,
+        completed: state.completed,
+        min_completion: state.min_completion,
+        max_runtime_secs: state.max_runtime.map(|d| d.as_secs()),
+        pinned: &state.pinned,
+        mutable_positions: &state.mutable,
+        tags: &state.tags,
+        seed: state.seed,
+        board: &state.board,
+        direction: state.config.direction,
+        alphabet: state.config.alphabet.iter().collect(),
+        workers,
+        started_at_ms,
+        generated_at_ms: SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |d| d.as_millis()),
+        keyboards: ranked.len(),
+        sha256: format!("{:x}", Sha256::digest(&json)),
+    };
+    let body = serde_json::to_string_pretty(&manifest).expect("serializable manifest");
+    Ok(([(CONTENT_TYPE, "application/json")], body))
+}
+
+/// every result as `rank,id,score,layout` rows, best first
+async fn export_csv(
+    State(shared_state): State<SharedState>,
+    Query(CsvReq { precision, header }): Query<CsvReq>,
+) -> Response {
+    if let Err(e) = check_precision(precision) {
+        return (StatusCode::BAD_REQUEST, e).into_response()
+    }
+    let state = shared_state.lock().await;
+    let mut csv = String::new();
+    if header.unwrap_or(true) {
+        csv.push_str("rank,id,score,layout\n");
+    }
+    for (rank, k) in ranked(&state.keyboards, state.config.direction).into_iter().enumerate() {
+        let score = match precision {
+            Some(places) => format!("{:.*}", places, k.score),
+            None => k.score.to_string(),
+        };
+        let layout = format!("\"{}\"", layout_string(k).replace('"', "\"\""));
+        csv.push_str(&format!("{},{},{},{}\n", rank, k.id, score, layout));
+    }
+    (
+        [
+            (CONTENT_TYPE, "text/csv; charset=utf-8"),
+            (CONTENT_DISPOSITION, "attachment; filename=\"keyboards.csv\""),
+        ],
+        csv,
+    ).into_response()
+}
+
+fn check_precision(precision: Option<usize>) -> Result<(), String> {
+    match precision {
+        Some(places) if places > MAX_PRECISION => {
+            Err(format!("precision must be at most {} decimal places", MAX_PRECISION))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// `score` to `precision` decimal places. f32s always print as the shortest
+/// string that reads back the same, so a rounded score prints rounded.
+fn rounded(score: f32, precision: Option<usize>) -> f32 {
+    match precision {
+        Some(places) => {
+            let scale = 10f64.powi(places as i32);
+            ((score as f64 * scale).round() / scale) as f32
+        }
+        None => score,
+    }
+}
+
+/// adds a layout shared from elsewhere to the results. without a score it
+/// gets the coordinator's effort score, so it ranks among comparable layouts
+/// instead of jumping to the top.
+async fn import_layout(
+    State(shared_state): State<SharedState>,
+    Form(ImportLayoutReq { layout, score }): Form<ImportLayoutReq>,
+) -> Html<String> {
+    let keys = match parse_layout(&layout) {
+        Ok(keys) => keys,
+        Err(e) => return Html(hyprtxt!("p" {
+            "style"="color: red;"
+            $: escape_html(&e)
+        })),
+    };
+    let mut keyboard = Keyboard { id: 0, score: 0.0, keys, metrics: BTreeMap::new(), generation: None, parent_id: None };
+    keyboard.score = match score.trim() {
+        "" => effort::score_layout(&keyboard, effort::FreqTable::english()),
+        score => match score.parse::<f32>() {
+            Ok(score) if score.is_finite() => score,
+            _ => return Html(hyprtxt!("p" {
+                "style"="color: red;"
+                $: "score must be a number"
+            })),
+        },
+    };
+
+    let mut state = shared_state.lock().await;
+    if !is_permutation(&keyboard, &state.config.alphabet) {
+        return Html(hyprtxt!("p" {
+            "style"="color: red;"
+            $: "layout must use every glyph of the alphabet exactly once"
+        }))
+    }
+    let score = keyboard.score;
+    let id = state.insert_keyboard(keyboard);
+    state.trim_keyboards();
+    let Some((rank, _)) = state.keyboard(id) else {
+        return Html(hyprtxt!("p" {
+            $: "Scored "
+            $: score
+            $: ", below every kept keyboard, so it was dropped"
+        }))
+    };
+    Html(hyprtxt!("p" {
+        $: "Imported with score "
+        $: score
+        $: " at "
+        "a" {
+            "href"=format!("/keyboard/{}", id)
+            $: format!("rank {}", rank)
+        }
+        $: ", "
+        "a" {
+            "href"=format!("/vs-baseline/{}", id)
+            $: "compare results to it"
+        }
+    }))
+}
+
+/// reads a `layout_string`, the row separators being optional
+fn parse_layout(input: &str) -> Result<[Key; 47], String> {
+    let chars: Vec<char> = input.trim().chars().collect();
+    let glyphs: Vec<char> = if chars.len() == 47 + ROWS.len() - 1 {
+        let separators = ROWS[1..].iter().enumerate().map(|(i, &(start, _))| start + i);
+        for at in separators {
+            if chars[at] != '/' {
+                return Err(format!("expected a / between rows at character {}", at))
+            }
+        }
+        chars
+            .iter()
+            .enumerate()
+            .filter(|&(i, _)| !ROWS[1..].iter().enumerate().any(|(r, &(start, _))| start + r == i))
+            .map(|(_, &c)| c)
+            .collect()
+    } else {
+        chars
+    };
+    if glyphs.len() != 47 {
+        return Err(format!("layout has {} keys, expected 47", glyphs.len()))
+    }
+
+    let mut keys = [Key { lower: ' ', upper: ' ' }; 47];
+    for (i, &upper) in glyphs.iter().enumerate() {
+        if upper.is_whitespace() || upper.is_control() {
+            return Err(format!("key {} isn't a printable glyph", i))
+        }
+        if glyphs[..i].contains(&upper) {
+            return Err(format!("{} appears more than once", upper))
+        }
+        let lower = match upper {
+            'a'..='z' => upper.to_ascii_uppercase(),
+            _ => SHIFT_PAIRS.iter().find(|(b, _)| *b == upper).map_or(upper, |(_, s)| *s),
+        };
+        keys[i] = Key { lower, upper };
+    }
+    Ok(keys)
+}
+
+/// the `upper` glyphs row by row, rows separated by `/`
+fn layout_string(keyboard: &Keyboard) -> String {
+    ROWS.iter()
+        .map(|&(start, end)| keyboard.keys[start..end].iter().map(|k| k.upper).collect::<String>())
+        .collect::<Vec<String>>()
+        .join("/")
+}
+
+/// qmk keycode producing `glyph`, shifted glyphs use the `S()` modifier
+fn qmk_keycode(glyph: char) -> String {
+    let base = |c: char| -> Option<String> {
+        let code = match c {
+            'a'..='z' | '1'..='9' | '0' => return Some(format!("KC_{}", c.to_ascii_uppercase())),
+            '`' => "KC_GRV",
+            '-' => "KC_MINS",
+            '=' => "KC_EQL",
+            '[' => "KC_LBRC",
+            ']' => "KC_RBRC",
+            '\\' => "KC_BSLS",
+            ';' => "KC_SCLN",
+            '\'' => "KC_QUOT",
+            ',' => "KC_COMM",
+            '.' => "KC_DOT",
+            '/' => "KC_SLSH",
+            ' ' => "KC_SPC",
+            _ => return None,
+        };
+        Some(code.to_string())
+    };
+    if let Some(code) = base(glyph) {
+        return code
+    }
+    let unshifted = match glyph {
+        'A'..='Z' => Some(glyph.to_ascii_lowercase()),
+        _ => SHIFT_PAIRS.iter().find(|(_, s)| *s == glyph).map(|(b, _)| *b),
+    };
+    unshifted
+        .and_then(base)
+        .map_or("KC_NO".to_string(), |code| format!("S({})", code))
+}
+
+/// keymap.c source with the keyboard's base layer as layer 0, laid out for
+/// `board` if it's one we know and the generic 47 key `LAYOUT` otherwise
+fn qmk_keymap(keyboard: &Keyboard, job_name: &str, rank: usize, board: &str) -> String {
+    let join = |codes: Vec<String>| format!("        {}", codes.join(", "));
+    let (layout_macro, rows) = match boards::find(board) {
+        Some(board) => (board.layout_macro, board.rows
+            .iter()
+            .map(|row| join(row
+                .iter()
+                .map(|slot| match *slot {
+                    boards::Slot::Key(pos) => qmk_keycode(keyboard.keys[pos].upper),
+                    boards::Slot::Code(code) => code.to_string(),
+                })
+                .collect()))
+            .collect::<Vec<String>>()),
+        None => ("LAYOUT", ROWS
+            .iter()
+            .map(|&(start, end)| join(keyboard.keys[start..end]
+                .iter()
+                .map(|key| qmk_keycode(key.upper))
+                .collect()))
+            .collect::<Vec<String>>()),
+    };
+    format!(
+        "// {} rank {}, score {}\n\
+        #include QMK_KEYBOARD_H\n\n\
+        const uint16_t PROGMEM keymaps[][MATRIX_ROWS][MATRIX_COLS] = {{\n    \
+        [0] = {}(\n{}\n    )\n}};\n",
+        job_name, rank, keyboard.score, layout_macro, rows.join(",\n"),
+    )
+}
+
+/// how far each row of `ROWS` is shifted right on `board`, in key widths
+fn row_offsets(board: &str) -> [f32; 4] {
+    boards::find(board).map_or(ROW_OFFSETS, |b| b.offsets)
+}
+
+/// raw data for keyboard-layout-editor.com, one array per row with each key
+/// labeled shift over base. on the generic board the last key of the second
+/// row is wide, where `\` sits on ansi.
+fn kle_layout(keyboard: &Keyboard, board: &str) -> serde_json::Value {
+    let generic = boards::find(board).is_none();
+    let rows = ROWS
+        .iter()
+        .zip(row_offsets(board))
+        .map(|(&(start, end), offset)| {
+            let mut row = vec![];
+            if offset > 0.0 {
+                row.push(serde_json::json!({ "x": offset }));
+            }
+            for i in start..end {
+                if generic && i == ROWS[1].1 - 1 {
+                    row.push(serde_json::json!({ "w": 1.5 }));
+                }
+                let key = &keyboard.keys[i];
+                row.push(format!("{}\n{}", key.lower, key.upper).into());
+            }
+            serde_json::Value::Array(row)
+        })
+        .collect();
+    serde_json::Value::Array(rows)
+}
+
+/// keyboards best first
+fn ranked(keyboards: &[Keyboard], direction: ScoreDirection) -> Vec<&Keyboard> {
+    let mut ranked: Vec<&Keyboard> = keyboards.iter().collect();
+    ranked.sort_by(|a, b| rank_cmp(direction, a, b));
+    ranked
+}
+
+/// better score first, ties going to whichever came in first
+fn rank_cmp(direction: ScoreDirection, a: &Keyboard, b: &Keyboard) -> Ordering {
+    direction.cmp(a.score, b.score).then(a.id.cmp(&b.id))
+}
+
+/// the keyboard's glyphs in order, for spotting the same layout twice
+fn layout(keyboard: &Keyboard) -> Vec<(char, char)> {
+    keyboard.keys.iter().map(|k| (k.upper, k.lower)).collect()
+}
+
+fn best(keyboards: &[Keyboard], direction: ScoreDirection) -> Option<&Keyboard> {
+    keyboards
+        .iter()
+        .min_by(|a, b| direction.cmp(a.score, b.score))
+}
+
+fn progress(state: &AppState) -> String {
+    let done = state.completed as f32 / state.batches.max(1) as f32;
+    let counts = hyprtxt!(
+        "p" {
+            $: state.completed
+            $: "/"
+            $: state.batches
+            $: format!(" batches ({:.0}%)", done * 100.0)
+            $: if state.required() < state.batches {
+                format!(", finishing at {}", state.required())
+            } else {
+                String::new()
+            }
+            $: match state.deadline {
+                Some(deadline) if state.is_running() => format!(
+                    ", {}s left",
+                    deadline.saturating_duration_since(Instant::now()).as_secs(),
+                ),
+                _ => String::new(),
+            }
+            $: match state.empty_batches {
+                0 => String::new(),
+                1 => ", 1 came back empty".to_string(),
+                n => format!(", {} came back empty", n),
+            }
+        }
+    );
+    counts + &moving_positions(state) + &best_change(state) + &last_result(state) + &invalid_counts(state)
+}
+
+/// how the current best differs from the one it replaced
+fn best_change(state: &AppState) -> String {
+    let (Some(previous), Some(current)) = (&state.previous_best, state.best()) else {
+        return String::new()
+    };
+    let changed = changed_positions(previous, current);
+    hyprtxt!(
+        "p" {
+            $: format!(
+                "Best went from {} to {} ({:+}), {} positions changed: ",
+                previous.score,
+                current.score,
+                current.score - previous.score,
+                changed.len(),
+            )
+            $: changed.iter().map(usize::to_string).collect::<Vec<String>>().join(" ")
+        }
+    )
+}
+
+/// positions where the two keyboards have different keys
+fn changed_positions(a: &Keyboard, b: &Keyboard) -> Vec<usize> {
+    a.keys
+        .iter()
+        .zip(&b.keys)
+        .enumerate()
+        .filter(|(_, (a, b))| a.upper != b.upper || a.lower != b.lower)
+        .map(|(i, _)| i)
+        .collect()
+}
+
+/// which positions the job lets move, nothing when it's all of them
+fn moving_positions(state: &AppState) -> String {
+    if state.mutable.is_empty() {
+        return String::new()
+    }
+    hyprtxt!(
+        "p" {
+            $: format!("Only {} of 47 positions move: ", state.mutable.len())
+            $: state.mutable.iter().map(usize::to_string).collect::<Vec<String>>().join(" ")
+        }
+    )
+}
+
+/// a banner for a job that says it's running but hasn't had a batch back in
+/// `stale_after`, with ways out. force-idle is an admin endpoint, so its
+/// button asks for the token and only shows when one is set.
+fn stall_warning(state: &AppState) -> String {
+    let Some(quiet) = state.last_result.filter(|_| state.is_running()).map(|at| at.elapsed()) else {
+        return String::new()
+    };
+    if quiet <= state.config.stale_after {
+        return String::new()
+    }
+    let force_idle = match state.config.auth_token {
+        Some(_) => hyprtxt!(
+            "button" {
+                "hx-post"="/admin/force-idle"
+                "hx-prompt"="admin token"
+                "hx-target"="#status"
+                "data-admin"="true"
+                $: "Force Idle"
+            }
+        ),
+        None => String::new(),
+    };
+    hyprtxt!(
+        "div" {
+            "style"="border: 2px solid red; padding: 0.5em;"
+            "p" {
+                $: format!(
+                    "Job {} is possibly stalled, no batch has come back in {}s. ",
+                    escape_html(&state.job_name),
+                    quiet.as_secs(),
+                )
+                $: "Stopping it keeps the results so far, forcing idle also drops tasks that won't stop."
+            }
+            "button" {
+                "hx-post"="/stop-all"
+                "hx-target"="#status"
+                $: "Stop All"
+            }
+            $: force_idle
+        }
+    )
+}
+
+/// how long the running job has gone without a finished batch, in red once
+/// that's past `stale_after` since the whole pool may have stalled
+fn last_result(state: &AppState) -> String {
+    let Some(at) = state.last_result.filter(|_| state.is_running()) else {
+        return String::new()
+    };
+    let elapsed = at.elapsed();
+    let heard_back = !state.acked.is_empty() || state.empty_batches > 0;
+    hyprtxt!(
+        "p" {
+            "style"=if elapsed > state.config.stale_after { "color: red;" } else { "" }
+            $: if heard_back {
+                format!("last result: {}s ago", elapsed.as_secs())
+            } else {
+                format!("no results yet, started {}s ago", elapsed.as_secs())
+            }
+        }
+    )
+}
+
+/// a note when none of the job's workers can be reached, so dispatch is on
+/// hold until one comes back
+fn waiting_for_workers(state: &AppState) -> String {
+    let down = state.inflight.keys().filter(|h| state.unhealthy.contains(*h)).count();
+    if state.inflight.is_empty() || down < state.inflight.len() {
+        return String::new()
+    }
+    hyprtxt!(
+        "p" {
+            "style"="color: orange;"
+            $: format!(
+                "Waiting for workers: all {} are unreachable, retrying every {}s",
+                down,
+                state.config.probe_interval.as_secs(),
+            )
+        }
+    )
+}
+
+/// how many batches each host is working on, with controls for its limit.
+/// http workers take one batch at a time, so for them a limit of 0 pauses the
+/// host and anything above 1 acts like 1
+fn inflight_table(state: &AppState) -> String {
+    let limit_button = |host: &str, limit: usize, label: &str| hyprtxt!(
+        "form" {
+            "hx-post"="/set-inflight"
+            "hx-target"="#inflight"
+            "hx-swap"="outerHTML"
+            "style"="display: inline;"
+            "input" {
+                "type"="hidden"
+                "name"="host"
+                "value"=escape_html(host)
+            }
+            "input" {
+                "type"="hidden"
+                "name"="limit"
+                "value"=limit
+            }
+            "button" {
+                "type"="submit"
+                $: label
+            }
+        }
+    );
+    let rows = state.hosts
+        .iter()
+        .map(|host| {
+            let limit = state.inflight_limit(host);
+            hyprtxt!(
+                "tr" {
+                    "td" {
+                        $: escape_html(host)
+                        $: if state.unhealthy.contains(host) { " (unreachable)" } else { "" }
+                    }
+                    "td" { $: state.inflight.get(host).copied().unwrap_or(0) }
+                    "td" {
+                        $: match state.batch_progress.get(host) {
+                            Some(&(done, size)) if size > 0 => hyprtxt!(
+                                "progress" {
+                                    "value"=done
+                                    "max"=size
+                                }
+                            ) + &format!(" {}/{}", done, size),
+                            _ => String::new(),
+                        }
+                    }
+                    "td" {
+                        $: limit_button(host, limit.saturating_sub(1), "-")
+                        $: " "
+                        $: limit
+                        $: " "
+                        $: limit_button(host, limit + 1, "+")
+                    }
+                }
+            )
+        })
+        .collect::<String>();
+    hyprtxt!(
+        "table" {
+            "id"="inflight"
+            "tr" {
+                "th" { $: "Host" }
+                "th" { $: "In Flight" }
+                "th" { $: "Current Batch" }
+                "th" { $: "Limit" }
+            }
+            $: rows
+        }
+    )
+}
+
+/// whether `host` has a batch out that `/restart-batch` could take back. the
+/// in-process worker's batches can't be interrupted.
+fn can_restart(state: &AppState, host: &str) -> bool {
+    host != LOCAL_HOST && state.is_running() && state.inflight.get(host).is_some_and(|&n| n > 0)
+}
+
+fn restart_button(host: &str) -> String {
+    hyprtxt!(
+        "form" {
+            "hx-post"="/restart-batch"
+            "hx-target"="#status"
+            "hx-swap"="innerHTML"
+            "style"="display: inline;"
+            "input" {
+                "type"="hidden"
+                "name"="host"
+                "value"=escape_html(host)
+            }
+            $: " "
+            "button" {
+                "type"="submit"
+                $: "Restart Batch"
+            }
+        }
+    )
+}
+
+fn remove_button(host: &str) -> String {
+    hyprtxt!(
+        "form" {
+            "hx-post"="/remove-server"
+            "hx-target"="#status"
+            "hx-swap"="innerHTML"
+            "style"="display: inline;"
+            "input" {
+                "type"="hidden"
+                "name"="host"
+                "value"=escape_html(host)
+            }
+            $: " "
+            "button" {
+                "type"="submit"
+                $: "Remove"
+            }
+        }
+    )
+}
+
+/// has `host`'s task drop the batch it's on and claim another, leaving the
+/// worker in the pool. it happens on the task's next poll.
+async fn restart_batch(
+    State(shared_state): State<SharedState>,
+    Form(RestartBatchReq { host }): Form<RestartBatchReq>,
+) -> Html<String> {
+    let mut state = shared_state.lock().await;
+    if !state.hosts.contains(&host) {
+        return job_error("no such host")
+    }
+    if !can_restart(&state, &host) {
+        return job_error("that host has no batch to restart")
+    }
+    state.restarts.insert(host);
+    Html(status(&state, &UpdateReq::default()))
+}
+
+/// changes how many batches a host may hold, taking effect on its next claim
+async fn set_inflight(
+    State(shared_state): State<SharedState>,
+    Form(SetInflightReq { host, limit }): Form<SetInflightReq>,
+) -> Html<String> {
+    let mut state = shared_state.lock().await;
+    if !state.hosts.contains(&host) {
+        return job_error("no such host")
+    }
+    state.inflight_limits.insert(host, limit);
+    save_hosts(&state).await;
+    Html(inflight_table(&state))
+}
+
+async fn favorite(
+    State(shared_state): State<SharedState>,
+    Path(id): Path<u64>,
+) -> Result<Html<String>, (StatusCode, &'static str)> {
+    let mut state = shared_state.lock().await;
+    if !state.favorites.iter().any(|k| k.id == id) {
+        let (_, keyboard) = state.keyboard(id).ok_or((StatusCode::NOT_FOUND, "no keyboard with that id"))?;
+        let keyboard = keyboard.clone();
+        state.favorites.push(keyboard);
+    }
+    Ok(Html(favorites(&state)))
+}
+
+async fn unfavorite(State(shared_state): State<SharedState>, Path(id): Path<u64>) -> Html<String> {
+    let mut state = shared_state.lock().await;
+    state.favorites.retain(|k| k.id != id);
+    Html(favorites(&state))
+}
+
+/// the favorited keyboards, best first
+fn favorites(state: &AppState) -> String {
+    if state.favorites.is_empty() {
+        return hyprtxt!("div" { "id"="favorites" })
+    }
+    hyprtxt!(
+        "div" {
+            "id"="favorites"
+            "h3" { $: "Favorites" }
+            "table" {
+                "class"="leaderboard"
+                "tr" {
+                    "th" { $: "Score" }
+                    "th" { $: "Layout" }
+                    "th" { $: "Keep" }
+                }
+                $: ranked(&state.favorites, state.config.direction)
                     .iter()
-                    .map(|key| hyprtxt!("button" {
-                        "class"="button"
-                        $: key.upper
+                    .take(state.config.max_rendered)
+                    .map(|k| hyprtxt!("tr" {
+                        "td" { $: k.score }
+                        "td" { "code" { $: escape_html(&layout_string(k)) } }
+                        "td" {
+                            "button" {
+                                "hx-post"=format!("/unfavorite/{}", k.id)
+                                "hx-target"="#favorites"
+                                "hx-swap"="outerHTML"
+                                $: "remove"
+                            }
+                        }
                     }))
                     .collect::<Vec<String>>()
                     .concat()
             }
-            "div" {
-                "class"="row"
-                $: &keyboard.keys[26..37]
+            $: truncation_note(state.favorites.len().min(state.config.max_rendered), state.favorites.len(), "/debug/state")
+        }
+    )
+}
+
+/// a banner when hosts are laying out different glyphs, since ranking their
+/// keyboards against each other means nothing then
+fn key_set_warning(state: &AppState) -> String {
+    let mut sets: Vec<&BTreeSet<char>> = state.glyph_sets.values().collect();
+    sets.sort();
+    sets.dedup();
+    if sets.len() < 2 {
+        return String::new()
+    }
+    // hosts grouped by the set they use
+    let groups: Vec<String> = sets
+        .iter()
+        .map(|set| {
+            let mut hosts: Vec<&str> = state.glyph_sets
+                .iter()
+                .filter(|(_, s)| s == set)
+                .map(|(host, _)| host.as_str())
+                .collect();
+            hosts.sort_unstable();
+            escape_html(&hosts.join(", "))
+        })
+        .collect();
+    hyprtxt!(
+        "h3" {
+            "style"="color: white; background: red; padding: 0.5em;"
+            $: format!(
+                "WARNING: workers are laying out {} different key sets, so their results aren't comparable: {}",
+                sets.len(),
+                groups.join(" vs "),
+            )
+        }
+    )
+}
+
+/// hosts that have sent back keyboards that weren't permutations
+fn invalid_counts(state: &AppState) -> String {
+    if state.invalid.is_empty() {
+        return String::new()
+    }
+    let mut hosts: Vec<(&String, &usize)> = state.invalid.iter().collect();
+    hosts.sort();
+    hyprtxt!(
+        "p" {
+            "style"="color: red;"
+            $: "Invalid keyboards rejected: "
+            $: hosts
+                .iter()
+                .map(|(host, count)| format!("{} from {}", count, escape_html(host)))
+                .collect::<Vec<_>>()
+                .join(", ")
+        }
+    )
+}
+
+/// best keyboard and leaderboard, hiding keyboards that don't meet `min_score`
+fn results(state: &AppState, min_score: Option<f32>, labels: Labels, sort: &str, top: Option<usize>) -> String {
+    let meets = |k: &&Keyboard| min_score.is_none_or(|m| state.config.direction.meets(k.score, m));
+    let (shown, total): (Vec<&Keyboard>, usize) = if sort.is_empty() || sort == "score" {
+        // leaders failing the threshold means everything after them does too
+        let shown: Vec<&Keyboard> = state.leaders.iter().take_while(meets).collect();
+        let total = match shown.len() {
+            n if n < state.leaders.len() => n,
+            _ => state.keyboards.iter().filter(meets).count(),
+        };
+        (shown, total)
+    } else {
+        let mut shown: Vec<&Keyboard> = ranked(&state.keyboards, state.config.direction)
+            .into_iter()
+            .filter(meets)
+            .collect();
+        // keyboards without the metric go last, still by score
+        shown.sort_by(|a, b| match (a.metrics.get(sort), b.metrics.get(sort)) {
+            (Some(&a), Some(&b)) => state.config.direction.cmp(a, b),
+            (a, b) => b.is_some().cmp(&a.is_some()),
+        });
+        let total = shown.len();
+        (shown, total)
+    };
+    let hidden = state.keyboards.len() - total;
+    hyprtxt!(
+        "div" {
+            $: shown.first().map_or(String::new(), |k| lockable_keyboard(k, state, labels))
+            $: shown.first().map_or(String::new(), |k| same_finger_summary(k))
+            $: leaderboard(&shown, total, top.unwrap_or(LEADERBOARD_SIZE), state.config.max_rendered)
+            $: if hidden > 0 {
+                hyprtxt!("p" {
+                    $: hidden
+                    $: " keyboards hidden by the score threshold"
+                })
+            } else {
+                String::new()
+            }
+        }
+    )
+}
+
+/// how much of the sample text `keyboard` types with one finger twice in a
+/// row, and the pairs that do it most
+fn same_finger_summary(keyboard: &Keyboard) -> String {
+    let conflicts = effort::same_finger_bigrams(keyboard, effort::FreqTable::english());
+    if conflicts.is_empty() {
+        return String::new()
+    }
+    let total: f32 = conflicts.iter().map(|(_, share)| share).sum();
+    let worst = conflicts
+        .iter()
+        .take(SFB_HIGHLIGHTED)
+        .map(|&((a, b), share)| {
+            let pair = format!("{}{}", keyboard.keys[a].upper, keyboard.keys[b].upper);
+            format!("{} {:.1}%", escape_html(&pair), share * 100.0)
+        })
+        .collect::<Vec<String>>()
+        .join(", ");
+    hyprtxt!("p" {
+        $: format!("Same finger bigrams: {:.1}% of the sample's bigrams, most often {}", total * 100.0, worst)
+    })
+}
+
+/// the top of the leaderboard with just the scores
+fn scoreboard(ranked: &[&Keyboard]) -> String {
+    if ranked.is_empty() { return "".to_string() }
+    let metrics: BTreeSet<&String> = ranked
+        .iter()
+        .take(LEADERBOARD_SIZE)
+        .flat_map(|k| k.metrics.keys())
+        .collect();
+    hyprtxt!(
+        "table" {
+            "class"="leaderboard"
+            "tr" {
+                "th" { $: "Rank" }
+                "th" { $: "Score" }
+                $: metrics
                     .iter()
-                    .map(|key| hyprtxt!("button" {
-                        "class"="button"
-                        $: key.upper
-                    }))
+                    .map(|name| hyprtxt!("th" { $: escape_html(name) }))
                     .collect::<Vec<String>>()
                     .concat()
             }
-            "div" {
-                "class"="row"
-                $: &keyboard.keys[37..47]
+            $: ranked
+                .iter()
+                .take(LEADERBOARD_SIZE)
+                .enumerate()
+                .map(|(rank, k)| hyprtxt!("tr" {
+                    "td" { $: rank }
+                    "td" { $: k.score }
+                    $: metrics
+                        .iter()
+                        .map(|&name| hyprtxt!("td" {
+                            $: k.metrics.get(name).map_or(String::new(), f32::to_string)
+                        }))
+                        .collect::<Vec<String>>()
+                        .concat()
+                }))
+                .collect::<Vec<String>>()
+                .concat()
+        }
+    )
+}
+
+/// the first `rows` of `ranked`, no more than `max_rendered` of them. `ranked`
+/// may be only the top of the `total` keyboards shown.
+fn leaderboard(ranked: &[&Keyboard], total: usize, rows: usize, max_rendered: usize) -> String {
+    if ranked.is_empty() { return "".to_string() }
+    let wanted = rows.min(total);
+    let rows = wanted.min(max_rendered).min(ranked.len());
+    let metrics: BTreeSet<&String> = ranked
+        .iter()
+        .take(rows)
+        .flat_map(|k| k.metrics.keys())
+        .collect();
+    hyprtxt!(
+        "table" {
+            "class"="leaderboard"
+            "tr" {
+                "th" { $: "Rank" }
+                "th" { $: "Score" }
+                $: metrics
                     .iter()
-                    .map(|key| hyprtxt!("button" {
-                        "class"="button"
-                        $: key.upper
-                    }))
+                    .map(|name| hyprtxt!("th" { $: escape_html(name) }))
                     .collect::<Vec<String>>()
                     .concat()
+                "th" { $: "Export" }
+                "th" { $: "Layout" }
+                "th" { $: "Check" }
+                "th" { $: "Keep" }
+            }
+            $: ranked
+                .iter()
+                .take(rows)
+                .enumerate()
+                .map(|(rank, k)| hyprtxt!("tr" {
+                    "td" {
+                        "a" {
+                            "href"=format!("/keyboard/{}", k.id)
+                            $: rank
+                        }
+                    }
+                    "td" { $: k.score }
+                    $: metrics
+                        .iter()
+                        .map(|&name| hyprtxt!("td" {
+                            $: k.metrics.get(name).map_or(String::new(), f32::to_string)
+                        }))
+                        .collect::<Vec<String>>()
+                        .concat()
+                    "td" {
+                        "a" {
+                            "href"=format!("/export/qmk/{}", k.id)
+                            $: "qmk"
+                        }
+                        $: " "
+                        "a" {
+                            "href"=format!("/export/kle/{}", k.id)
+                            $: "kle"
+                        }
+                    }
+                    "td" {
+                        "button" {
+                            "data-copy"=format!("/export/string/{}", k.id)
+                            $: "copy"
+                        }
+                    }
+                    "td" {
+                        "a" {
+                            "href"=format!("/rescore/{}", k.id)
+                            $: "rescore"
+                        }
+                    }
+                    "td" {
+                        "button" {
+                            "hx-post"=format!("/favorite/{}", k.id)
+                            "hx-target"="#favorites"
+                            "hx-swap"="outerHTML"
+                            $: "favorite"
+                        }
+                    }
+                }))
+                .collect::<Vec<String>>()
+                .concat()
+        }
+    ) + &truncation_note(rows, wanted, "/api/keyboards")
+}
+
+/// says a list was cut short at `max_rendered`, and where to find the rest
+fn truncation_note(shown: usize, total: usize, rest: &str) -> String {
+    if shown >= total {
+        return String::new()
+    }
+    hyprtxt!(
+        "p" {
+            $: format!("showing {} of {}, ", shown, total)
+            "a" {
+                "href"=rest
+                $: "see all"
             }
         }
     )
 }
+
+fn keyboard(keyboard: &Keyboard, pinned: &[(usize, char)], board: &str, labels: Labels) -> String {
+    layer(keyboard, pinned, None, row_offsets(board), |i, key| match labels {
+        Labels::Glyph => key.upper.to_string(),
+        Labels::Index => i.to_string(),
+    })
+}
+
+/// `keyboard` with keys that lock in place for the next job when clicked
+fn lockable_keyboard(keyboard: &Keyboard, state: &AppState, labels: Labels) -> String {
+    layer(keyboard, &state.pinned, Some(&state.locks), row_offsets(&state.board), |i, key| match labels {
+        Labels::Glyph => key.upper.to_string(),
+        Labels::Index => i.to_string(),
+    })
+}
+
+/// the base layer over the shifted one, each labeled
+fn keyboard_layers(keyboard: &Keyboard, pinned: &[(usize, char)], board: &str) -> String {
+    let offsets = row_offsets(board);
+    hyprtxt!(
+        "div" {
+            "h4" { $: "Base" }
+            $: layer(keyboard, pinned, None, offsets, |_, key| key.upper.to_string())
+            "h4" { $: "Shift" }
+            $: layer(keyboard, pinned, None, offsets, |_, key| key.lower.to_string())
+        }
+    )
+}
+
+/// the grid with each key labeled by `label`, given its position and key.
+/// hovering a key shows what it adds to the local effort score, and keys in
+/// the `SFB_HIGHLIGHTED` most common same finger pairs are outlined. with `locks`
+/// clicking a key toggles its lock, refilling the start form's pinned keys.
+/// rows are indented by `offsets`, in key widths.
+fn layer(
+    keyboard: &Keyboard,
+    pinned: &[(usize, char)],
+    locks: Option<&[(usize, char)]>,
+    offsets: [f32; 4],
+    label: impl Fn(usize, &Key) -> String,
+) -> String {
+    let costs = effort::position_costs(keyboard, effort::FreqTable::english());
+    let mut conflicts = effort::same_finger_bigrams(keyboard, effort::FreqTable::english());
+    conflicts.truncate(SFB_HIGHLIGHTED);
+    let (home_start, home_end) = ROWS[HOME_ROW];
+    hyprtxt!(
+        "div" {
+            "class"="keyboard"
+            $: ROWS
+                .iter()
+                .zip(offsets)
+                .map(|(&(start, end), offset)| hyprtxt!("div" {
+                    "class"="row"
+                    "style"=format!("margin-left: {}em;", offset * KEY_EM)
+                    $: (start..end)
+                        .map(|i| {
+                            let class = format!(
+                                "button{}{}{}{}",
+                                if pinned.iter().any(|(p, _)| *p == i) { " pinned" } else { "" },
+                                if (home_start..home_end).contains(&i) { " home" } else { "" },
+                                match locks {
+                                    Some(locks) if locks.iter().any(|(p, _)| *p == i) => " locked",
+                                    _ => "",
+                                },
+                                if conflicts.iter().any(|&((a, b), _)| a == i || b == i) { " sfb" } else { "" },
+                            );
+                            let title = format!("effort {:.3}", costs[i]) + &conflicts
+                                .iter()
+                                .filter(|&&((a, b), _)| a == i || b == i)
+                                .map(|&((a, b), share)| format!(
+                                    ", same finger as {} in {:.1}% of bigrams",
+                                    escape_html(&keyboard.keys[if a == i { b } else { a }].upper.to_string()),
+                                    share * 100.0,
+                                ))
+                                .collect::<String>();
+                            let text = escape_html(&label(i, &keyboard.keys[i]));
+                            match locks {
+                                Some(_) => hyprtxt!("button" {
+                                    "class"=class
+                                    "title"=title
+                                    "hx-post"="/toggle-lock"
+                                    "hx-vals"=escape_html(&format!("{{\"id\": {}, \"pos\": {}}}", keyboard.id, i))
+                                    "hx-target"="#pinned"
+                                    "hx-swap"="outerHTML"
+                                    $: text
+                                }),
+                                None => hyprtxt!("button" {
+                                    "class"=class
+                                    "title"=title
+                                    $: text
+                                }),
+                            }
+                        })
+                        .collect::<Vec<String>>()
+                        .concat()
+                }))
+                .collect::<Vec<String>>()
+                .concat()
+        }
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// state partway through `launch_job` for a job of `batches` batches on
+    /// `hosts`, without any worker tasks
+    fn running_job(hosts: &[&str], batches: usize) -> AppState {
+        let state = AppState {
+            hosts: hosts.iter().map(|h| h.to_string()).collect(),
+            job_name: "test".into(),
+            job_id: 1,
+            batches,
+            batch_size: 1,
+            claims: hosts.iter().map(|h| (h.to_string(), 0)).collect(),
+            inflight: hosts.iter().map(|h| (h.to_string(), 0)).collect(),
+            ..Default::default()
+        };
+        assert!(state.try_start());
+        state
+    }
+
+    /// a keyboard of the default alphabet in order, scoring `score`
+    fn keyboard(score: f32) -> Keyboard {
+        let mut keys = [Key { lower: ' ', upper: ' ' }; 47];
+        for (key, glyph) in keys.iter_mut().zip(DEFAULT_ALPHABET.chars()) {
+            *key = Key { lower: glyph, upper: glyph };
+        }
+        Keyboard { id: 0, score, keys, metrics: BTreeMap::new(), generation: None, parent_id: None }
+    }
+
+    #[test]
+    fn no_batch_is_claimed_after_the_job_finishes() {
+        let mut state = running_job(&["a", "b", "c"], 2);
+        assert!(matches!(claim_batch(&mut state, 1, "a"), Claim::Batch(0)));
+        assert!(matches!(claim_batch(&mut state, 1, "b"), Claim::Batch(1)));
+        assert!(matches!(claim_batch(&mut state, 1, "c"), Claim::Wait));
+        ingest_batch(&mut state, "a", 0, vec![keyboard(1.0)]);
+        ingest_batch(&mut state, "b", 1, vec![keyboard(2.0)]);
+        assert!(state.completed >= state.required());
+        finish_job(&mut state, JobOutcome::Complete);
+
+        for host in ["a", "b", "c"] {
+            assert!(matches!(claim_batch(&mut state, 1, host), Claim::Done));
+        }
+        assert_eq!(state.dispatched, 2);
+        assert!(state.requeued.is_empty());
+    }
+
+    #[tokio::test]
+    async fn a_job_without_workers_is_refused() {
+        let shared_state: SharedState = Arc::new(Mutex::new(AppState::default()));
+        let req: StartJobReq = serde_json::from_value(serde_json::json!({
+            "job_name": "empty",
+            "batch_size": 1,
+            "batches": 1,
+        }))
+        .unwrap();
+        let resp = start_job(State(shared_state.clone()), Form(req)).await;
+        let body = hyper::body::to_bytes(resp.into_body()).await.unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("add a worker before starting a job"));
+
+        let state = shared_state.lock().await;
+        assert!(!state.is_running());
+        assert_eq!(state.job_id, 0);
+        assert!(state.tasks.is_empty());
+    }
+
+    /// runs `body` through `decode_update` as a worker's answer of `content_type`
+    async fn decode(content_type: &str, body: Vec<u8>) -> Result<UpdateResp, String> {
+        let resp = axum::http::Response::builder()
+            .header(CONTENT_TYPE, content_type)
+            .body(body)
+            .unwrap();
+        decode_update(reqwest::Response::from(resp)).await
+    }
+
+    #[tokio::test]
+    async fn msgpack_updates_allow_extra_and_missing_fields() {
+        let newer = serde_json::json!({
+            "InProgress": { "batch_size": 10, "completed": 4, "eta_secs": 12 },
+        });
+        let decoded = decode("application/msgpack", rmp_serde::to_vec_named(&newer).unwrap()).await;
+        assert!(matches!(decoded, Ok(UpdateResp::InProgress { batch_size: 10, completed: 4 })));
+
+        let older = serde_json::json!({ "InProgress": {} });
+        let decoded = decode("application/msgpack", rmp_serde::to_vec_named(&older).unwrap()).await;
+        assert!(matches!(decoded, Ok(UpdateResp::InProgress { batch_size: 0, completed: 0 })));
+
+        let mut sent = serde_json::to_value(keyboard(3.5)).unwrap();
+        let fields = sent.as_object_mut().unwrap();
+        fields.remove("id");
+        fields.remove("metrics");
+        fields.insert("worker_version".into(), "2.0".into());
+        let complete = serde_json::json!({ "BatchComplete": { "keyboards": [sent], "elapsed_ms": 900 } });
+        let decoded = decode("application/msgpack", rmp_serde::to_vec_named(&complete).unwrap()).await;
+        let Ok(UpdateResp::BatchComplete { keyboards }) = decoded else {
+            panic!("batch didn't decode");
+        };
+        assert_eq!(keyboards.len(), 1);
+        assert_eq!(keyboards[0].score, 3.5);
+        assert!(keyboards[0].metrics.is_empty());
+        assert_eq!(layout_string(&keyboards[0]), layout_string(&keyboard(3.5)));
+    }
+
+    #[test]
+    fn non_finite_scores_never_lead() {
+        let mut state = running_job(&["a"], 2);
+        ingest_batch(&mut state, "a", 0, vec![keyboard(f32::NEG_INFINITY), keyboard(f32::NAN)]);
+        assert!(state.best().is_none());
+        assert!(state.keyboards.is_empty());
+
+        ingest_batch(&mut state, "a", 1, vec![keyboard(f32::INFINITY), keyboard(2.0)]);
+        assert_eq!(state.best().map(|k| k.score), Some(2.0));
+        assert_eq!(state.keyboards.len(), 1);
+        assert_eq!(state.invalid.get("a"), Some(&3));
+    }
+
+    #[tokio::test(flavor = "multi_thread", worker_threads = 4)]
+    async fn only_one_racing_start_wins() {
+        const RACERS: usize = 16;
+        let state = Arc::new(AppState::default());
+        let barrier = Arc::new(tokio::sync::Barrier::new(RACERS));
+        let racers: Vec<_> = (0..RACERS)
+            .map(|_| {
+                let (state, barrier) = (state.clone(), barrier.clone());
+                tokio::spawn(async move {
+                    barrier.wait().await;
+                    state.try_start()
+                })
+            })
+            .collect();
+        let mut won = 0;
+        for racer in racers {
+            if racer.await.unwrap() {
+                won += 1;
+            }
+        }
+        assert_eq!(won, 1);
+        assert!(state.is_running());
+    }
+
+    #[test]
+    fn a_batch_handed_in_twice_counts_once() {
+        let mut state = running_job(&["a", "b", "c"], 3);
+        assert!(matches!(claim_batch(&mut state, 1, "a"), Claim::Batch(0)));
+        assert!(matches!(claim_batch(&mut state, 1, "b"), Claim::Batch(1)));
+        ingest_batch(&mut state, "a", 0, vec![keyboard(1.0)]);
+        ingest_batch(&mut state, "a", 0, vec![keyboard(1.0)]);
+        assert_eq!(state.completed, 1);
+
+        // b's batch is requeued, then comes back from b and from c
+        requeue_batch(&mut state, "b", 1);
+        assert!(matches!(claim_batch(&mut state, 1, "c"), Claim::Batch(1)));
+        ingest_batch(&mut state, "c", 1, vec![keyboard(1.0)]);
+        ingest_batch(&mut state, "b", 1, vec![keyboard(1.0)]);
+        assert_eq!(state.completed, 2);
+        assert_eq!(state.dispatched, 2);
+    }
+
+    #[test]
+    fn glyphs_can_be_strings_or_code_points() {
+        let key: Key = serde_json::from_str(r#"{"lower": "a", "upper": "\u00e9"}"#).unwrap();
+        assert_eq!((key.lower, key.upper), ('a', '\u{e9}'));
+
+        let key: Key = serde_json::from_str(r#"{"lower": 65, "upper": 128512}"#).unwrap();
+        assert_eq!((key.lower, key.upper), ('A', '\u{1f600}'));
+
+        let Err(e) = serde_json::from_str::<Key>(r#"{"lower": "ab", "upper": "c"}"#) else {
+            panic!("a two character glyph was accepted");
+        };
+        assert!(e.to_string().contains("a single character or a unicode code point"));
+        assert!(serde_json::from_str::<Key>(r#"{"lower": "", "upper": "c"}"#).is_err());
+        assert!(serde_json::from_str::<Key>(r#"{"lower": 55296, "upper": "c"}"#).is_err());
+
+        let round_trip: Key = serde_json::from_str(&serde_json::to_string(&Key { lower: 'q', upper: 'q' }).unwrap()).unwrap();
+        assert_eq!((round_trip.lower, round_trip.upper), ('q', 'q'));
+    }
+
+    #[tokio::test]
+    async fn handed_in_batches_are_ingested_in_order() {
+        let (results, incoming) = mpsc::unbounded_channel();
+        let state = AppState { results: results.clone(), ..running_job(&["a", "b"], 3) };
+        let config = &state.config;
+        let job = JobHandle {
+            id: state.job_id,
+            encoding: config.encoding,
+            poll_interval: config.poll_interval,
+            probe_interval: config.probe_interval,
+            parse_retry: config.parse_retry,
+            init_timeout: config.init_timeout,
+            min_dispatch_interval: config.min_dispatch_interval,
+            client: state.client.clone(),
+            results,
+            stop: state.stop.clone(),
+        };
+        let shared_state: SharedState = Arc::new(Mutex::new(state));
+        tokio::spawn(run_ingest(shared_state.clone(), incoming));
+
+        {
+            // handing in doesn't wait on ingestion, which needs this lock
+            let mut state = shared_state.lock().await;
+            for (batch, (host, score)) in [("a", 3.0), ("b", 1.0), ("a", 2.0)].into_iter().enumerate() {
+                job.hand_in(&mut state, host, batch, vec![keyboard(score)]);
+            }
+            assert_eq!(state.completed, 0);
+        }
+
+        let finished = async {
+            while shared_state.lock().await.is_running() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(5), finished).await.expect("job finished");
+        let state = shared_state.lock().await;
+        assert_eq!(state.completed, 3);
+        assert_eq!(state.convergence, vec![(1, 3.0), (2, 1.0), (3, 1.0)]);
+        let first_ids: Vec<u64> = (0..3).map(|batch| state.batch_results[&batch].1.start).collect();
+        assert_eq!(first_ids, vec![0, 1, 2]);
+        assert!(matches!(state.history.last().map(|job| job.outcome), Some(JobOutcome::Complete)));
+    }
+
+    #[tokio::test]
+    async fn an_error_page_is_an_error_not_a_panic() {
+        let page = b"<html><body><h1>502 Bad Gateway</h1></body></html>".to_vec();
+        let Err(e) = decode("text/html", page).await else {
+            panic!("an html page decoded as an update");
+        };
+        assert!(e.contains("502 Bad Gateway"));
+
+        assert!(decode("application/json", br#"{"InProgress": {"batch_si"#.to_vec()).await.is_err());
+        assert!(decode("application/msgpack", b"<html>".to_vec()).await.is_err());
+    }
+
+    #[test]
+    fn a_reassigned_batch_keeps_the_better_result() {
+        let mut state = running_job(&["stale", "fresh"], 2);
+        ingest_batch(&mut state, "stale", 0, vec![keyboard(5.0), keyboard(6.0)]);
+        ingest_batch(&mut state, "fresh", 0, vec![keyboard(2.0)]);
+        assert_eq!(state.completed, 1);
+        assert_eq!(state.keyboards.iter().map(|k| k.score).collect::<Vec<f32>>(), vec![2.0]);
+        assert_eq!(state.best().map(|k| k.score), Some(2.0));
+
+        // a worse result coming back after the better one is dropped
+        ingest_batch(&mut state, "stale", 1, vec![keyboard(1.0)]);
+        ingest_batch(&mut state, "fresh", 1, vec![keyboard(4.0)]);
+        assert_eq!(state.completed, 2);
+        assert_eq!(state.keyboards.len(), 2);
+        assert_eq!(state.best().map(|k| k.score), Some(1.0));
+    }
+}
@@ -0,0 +1,95 @@
+//! raster rendering of a keyboard for sharing outside the browser
+
+use std::sync::OnceLock;
+
+use fontdue::{Font, FontSettings};
+use tiny_skia::{Color, FillRule, Paint, Path, PathBuilder, Pixmap, PremultipliedColorU8, Transform};
+
+use crate::{Keyboard, ROWS};
+
+/// DejaVu Sans Mono, see assets/DejaVu-LICENSE
+const FONT: &[u8] = include_bytes!("../assets/DejaVuSansMono.ttf");
+
+const KEY: f32 = 56.0;
+const GAP: f32 = 6.0;
+const PAD: f32 = 16.0;
+const RADIUS: f32 = 6.0;
+const LABEL_PX: f32 = 28.0;
+
+fn font() -> &'static Font {
+    static CELL: OnceLock<Font> = OnceLock::new();
+    CELL.get_or_init(|| Font::from_bytes(FONT, FontSettings::default()).expect("bundled font"))
+}
+
+fn rounded_rect(x: f32, y: f32, w: f32, h: f32, r: f32) -> Path {
+    let mut pb = PathBuilder::new();
+    pb.move_to(x + r, y);
+    pb.line_to(x + w - r, y);
+    pb.quad_to(x + w, y, x + w, y + r);
+    pb.line_to(x + w, y + h - r);
+    pb.quad_to(x + w, y + h, x + w - r, y + h);
+    pb.line_to(x + r, y + h);
+    pb.quad_to(x, y + h, x, y + h - r);
+    pb.line_to(x, y + r);
+    pb.quad_to(x, y, x + r, y);
+    pb.close();
+    pb.finish().expect("non-empty key outline")
+}
+
+/// blends a white glyph centered on (cx, cy) into the pixmap
+fn draw_glyph(pixmap: &mut Pixmap, glyph: char, cx: f32, cy: f32) {
+    let (metrics, coverage) = font().rasterize(glyph, LABEL_PX);
+    let baseline = (cy + LABEL_PX * 0.35) as i32;
+    let left = (cx - metrics.width as f32 / 2.0) as i32;
+    let top = baseline - metrics.height as i32 - metrics.ymin;
+    let width = pixmap.width() as i32;
+    let height = pixmap.height() as i32;
+    let pixels = pixmap.pixels_mut();
+
+    for (i, &alpha) in coverage.iter().enumerate() {
+        let x = left + (i % metrics.width) as i32;
+        let y = top + (i / metrics.width) as i32;
+        if alpha == 0 || x < 0 || y < 0 || x >= width || y >= height {
+            continue;
+        }
+        let pixel = &mut pixels[(y * width + x) as usize];
+        let blend = |dst: u8| ((255 * alpha as u32 + dst as u32 * (255 - alpha as u32)) / 255) as u8;
+        *pixel = PremultipliedColorU8::from_rgba(
+            blend(pixel.red()),
+            blend(pixel.green()),
+            blend(pixel.blue()),
+            255,
+        )
+        .unwrap();
+    }
+}
+
+/// draws the keyboard grid with its `upper` labels, each row shifted right by
+/// its offset in key widths, and returns png bytes
+pub fn render(keyboard: &Keyboard, offsets: [f32; 4]) -> Vec<u8> {
+    let widest = ROWS
+        .iter()
+        .zip(offsets)
+        .map(|(&(start, end), offset)| offset + (end - start) as f32)
+        .fold(0.0, f32::max);
+    let width = PAD * 2.0 + widest * KEY + (widest - 1.0) * GAP;
+    let height = PAD * 2.0 + ROWS.len() as f32 * (KEY + GAP) - GAP;
+
+    let mut pixmap = Pixmap::new(width.ceil() as u32, height.ceil() as u32).unwrap();
+    pixmap.fill(Color::from_rgba8(245, 245, 245, 255));
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(60, 60, 60, 255);
+    paint.anti_alias = true;
+
+    for (row, &(start, end)) in ROWS.iter().enumerate() {
+        let y = PAD + row as f32 * (KEY + GAP);
+        for (col, key) in keyboard.keys[start..end].iter().enumerate() {
+            let x = PAD + (offsets[row] + col as f32) * (KEY + GAP);
+            let outline = rounded_rect(x, y, KEY, KEY, RADIUS);
+            pixmap.fill_path(&outline, &paint, FillRule::Winding, Transform::identity(), None);
+            draw_glyph(&mut pixmap, key.upper, x + KEY / 2.0, y + KEY / 2.0);
+        }
+    }
+
+    pixmap.encode_png().expect("png encoding")
+}